@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{ContentStyle, Print, PrintStyledContent, StyledContent, Stylize},
+    terminal::{Clear, ClearType},
+};
+
+use crate::board::{Board, BoardState, PlayerSet};
+
+/// Columns per cell, two make cells about square in most fonts
+const CELL_WIDTH: u16 = 2;
+
+/// Draws the board as ANSI escape codes with its top left corner at `pos`,
+/// for terminals other than the one the game runs in
+///
+/// Cells are painted from [`Board::cell_looks`], one row of text per row of
+/// cells and no lines between them, so the board fits small remote
+/// terminals. Everything is queued, `out` has to be flushed afterwards.
+pub fn draw_board(board: &Board, pos: (u16, u16), out: &mut impl Write) -> io::Result<()> {
+    draw_cells(board.cell_looks(), pos, out)
+}
+
+/// Same as [`draw_board`] for cells that don't come from the board as it
+/// is, like a frame of the instant replay
+pub fn draw_cells(
+    looks: Vec<Vec<(char, ContentStyle)>>,
+    pos: (u16, u16),
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let height = looks.len() as u16;
+    let width = looks.first().map_or(0, Vec::len) as u16;
+    let edge = "─".repeat((width * CELL_WIDTH) as usize);
+
+    queue!(out, MoveTo(pos.0, pos.1), Print(format!("┌{}┐", edge)))?;
+    for (y, row) in looks.into_iter().enumerate() {
+        queue!(out, MoveTo(pos.0, pos.1 + 1 + y as u16), Print('│'))?;
+        for (ch, style) in row {
+            let text = ch.to_string().repeat(CELL_WIDTH as usize);
+            queue!(out, PrintStyledContent(StyledContent::new(style, text)))?;
+        }
+        queue!(out, Print('│'))?;
+    }
+    queue!(
+        out,
+        MoveTo(pos.0, pos.1 + height + 1),
+        Print(format!("└{}┘", edge))
+    )
+}
+
+/// Columns and rows [`draw_board`] covers, border included
+pub fn board_size(board: &Board) -> (u16, u16) {
+    let (width, height) = board.size();
+    (width as u16 * CELL_WIDTH + 2, height as u16 + 2)
+}
+
+/// Scores on the line `top`, the cells below them and `status` under the
+/// board, the view of a match everyone watching it gets
+pub fn draw_match(
+    board: &Board,
+    looks: Vec<Vec<(char, ContentStyle)>>,
+    top: u16,
+    status: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    queue!(out, MoveTo(0, top), Clear(ClearType::CurrentLine))?;
+    for (player, score) in board.get_scores().into_iter().enumerate() {
+        let score = format!("P{}: {}  ", player + 1, score);
+        queue!(
+            out,
+            PrintStyledContent(score.with(board.player_color(player)))
+        )?;
+    }
+    draw_cells(looks, (0, top + 1), out)?;
+    queue!(
+        out,
+        MoveTo(0, top + 1 + board_size(board).1),
+        Clear(ClearType::CurrentLine),
+        Print(status),
+    )
+}
+
+/// Whole view spectators get, a title line above [`draw_match`], ready for
+/// [`SpectatorHub::broadcast`](crate::net::spectate::SpectatorHub::broadcast)
+pub fn spectator_frame(
+    board: &Board,
+    looks: Vec<Vec<(char, ContentStyle)>>,
+    status: &str,
+) -> Vec<u8> {
+    let mut frame = Vec::new();
+    queue!(
+        frame,
+        MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        Print("Watching, the match goes on without you"),
+    )
+    .and_then(|()| draw_match(board, looks, 1, status, &mut frame))
+    .expect("Writing to a Vec doesn't fail");
+    frame
+}
+
+/// Who won the round, `None` while it's running
+pub fn round_result(state: BoardState) -> Option<String> {
+    let names = |players: PlayerSet| {
+        players
+            .iter()
+            .map(|p| format!("P{}", p + 1))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match state {
+        BoardState::Running => None,
+        BoardState::Won { player } => Some(format!("P{} won", player + 1)),
+        BoardState::Draw { players } => Some(format!("Draw between {}", names(players))),
+        BoardState::AllLost => Some("Nobody made it".into()),
+        BoardState::Filled { players } => Some(format!("Board is full, {} won", names(players))),
+    }
+}
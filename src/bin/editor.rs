@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use crossterm::{
+    event::{read, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{Color, ContentStyle, Stylize},
+};
+use double_snake::{
+    board::{Board, Dir},
+    helpers::is_key_release,
+    map::Map,
+    ui::{dims::IntoUidims, menu::pick, popup::popup, uibox::UiBox},
+};
+use terminal_renderer::{
+    canvas::CanvasLike,
+    drawable::{misc::RightAlignedStringExt, Drawable},
+    frame::Frame,
+    renderer::{Dims, Renderer},
+};
+
+const SIZES: [i32; 10] = [4, 6, 8, 10, 12, 14, 16, 20, 24, 32];
+
+#[derive(Clone, Copy)]
+enum Action {
+    Resume,
+    Save,
+    Reload,
+    Resize,
+    Quit,
+}
+
+struct Editor {
+    map: Map,
+    path: String,
+    cursor: Dims,
+    changed: bool,
+}
+
+impl Editor {
+    fn spawn_at(&self, pos: Dims) -> Option<usize> {
+        self.map.spawns.iter().position(|&s| s == pos)
+    }
+
+    /// Errors if the cursor is on a spawn, those have to be moved or removed
+    /// explicitly
+    fn check_no_spawn(&self) -> Result<(), String> {
+        match self.spawn_at(self.cursor) {
+            Some(player) => Err(format!("Cell has the spawn of P{}", player + 1)),
+            None => Ok(()),
+        }
+    }
+
+    fn move_cursor(&mut self, dir: Dir) -> Result<(), String> {
+        let next = dir.step(self.cursor);
+        if self.map.contains(next) {
+            self.cursor = next;
+        }
+        Ok(())
+    }
+
+    fn toggle_wall(&mut self) -> Result<(), String> {
+        self.check_no_spawn()?;
+        if !self.map.walls.remove(&self.cursor) {
+            self.map.walls.insert(self.cursor);
+            self.map.food.retain(|&f| f != self.cursor);
+        }
+        self.changed = true;
+        Ok(())
+    }
+
+    fn toggle_food(&mut self) -> Result<(), String> {
+        self.check_no_spawn()?;
+        if self.map.food.contains(&self.cursor) {
+            self.map.food.retain(|&f| f != self.cursor);
+        } else {
+            self.map.food.push(self.cursor);
+            self.map.walls.remove(&self.cursor);
+        }
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Places or moves the player's spawn to the cursor
+    fn place_spawn(&mut self, player: usize) -> Result<(), String> {
+        if player > self.map.spawns.len() {
+            return Err(format!("Place P{} first", self.map.spawns.len() + 1));
+        }
+        match self.spawn_at(self.cursor) {
+            Some(other) if other != player => {
+                return Err(format!("Cell has the spawn of P{}", other + 1))
+            }
+            _ => {}
+        }
+
+        self.map.walls.remove(&self.cursor);
+        self.map.food.retain(|&f| f != self.cursor);
+        if player == self.map.spawns.len() {
+            self.map.spawns.push(self.cursor);
+        } else {
+            self.map.spawns[player] = self.cursor;
+        }
+        self.changed = true;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        if let Some(player) = self.spawn_at(self.cursor) {
+            // Players are numbered by their spawns, so removing one in the
+            // middle would renumber the rest
+            if player + 1 != self.map.spawns.len() {
+                return Err(format!(
+                    "Only the last spawn, P{}, can be removed",
+                    self.map.spawns.len()
+                ));
+            }
+            self.map.spawns.pop();
+        }
+        self.map.walls.remove(&self.cursor);
+        self.map.food.retain(|&f| f != self.cursor);
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Cuts off everything outside of the new size, spawns are pushed inside
+    fn resize(&mut self, width: i32, height: i32) {
+        let clamp = |pos: Dims| (pos.0.min(width - 1), pos.1.min(height - 1));
+        let map = &mut self.map;
+        map.size = (width, height);
+        for spawn in map.spawns.iter_mut() {
+            *spawn = clamp(*spawn);
+        }
+
+        let spawns = &map.spawns;
+        let keep = |pos: &Dims| pos.0 < width && pos.1 < height && !spawns.contains(pos);
+        map.walls.retain(keep);
+        map.food.retain(keep);
+        self.cursor = clamp(self.cursor);
+        self.changed = true;
+    }
+
+    /// Checks what `Board` would refuse to start with
+    fn validate(&self) -> Result<(), String> {
+        let spawns = &self.map.spawns;
+        if spawns.len() < 2 {
+            return Err("Map needs spawns for at least 2 players".into());
+        }
+        for (i, spawn) in spawns.iter().enumerate() {
+            if let Some(other) = spawns[..i].iter().position(|s| s == spawn) {
+                return Err(format!("P{} and P{} share a spawn", other + 1, i + 1));
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        self.validate()?;
+        self.map
+            .save(&self.path)
+            .map_err(|err| format!("Cannot save map: {}", err))?;
+        self.changed = false;
+        Ok(())
+    }
+
+    fn reload(&mut self) -> Result<(), String> {
+        self.map = Map::load(&self.path).map_err(|err| err.to_string())?;
+        self.cursor = (0, 0);
+        self.changed = false;
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut impl CanvasLike) {
+        let board = Board::preview(self.map.clone());
+
+        "Arrows/WASD: move, Space: wall, *: food, 1-8: spawn, Del: clear, Esc: menu"
+            .draw((1, 0), frame);
+        format!(
+            "{}{}",
+            self.path,
+            if self.changed { " (modified)" } else { "" }
+        )
+        .draw((1, 1), frame);
+        (
+            format!(
+                "{}x{}, cursor at {}:{}",
+                self.map.size.0, self.map.size.1, self.cursor.0, self.cursor.1
+            )
+            .right(),
+            ContentStyle::new(),
+        )
+            .draw(
+                (1, 1),
+                &mut Frame::new(&mut *frame).l(board.render_size().0).mx(1),
+            );
+
+        board.draw((0, 2), frame);
+        let cursor_pos = (0, 2).into_ui()
+            + (1, 1).into_ui()
+            + (board.cell_size().into_ui() + board.border_size().into_ui()) * self.cursor.into_ui();
+        UiBox::new(
+            board.cell_size().into(),
+            ContentStyle::new().yellow().bold(),
+        )
+        .draw(cursor_pos.into(), frame);
+
+        (
+            format!(
+                "Spawns: {}, walls: {}, food: {}",
+                self.map.spawns.len(),
+                self.map.walls.len(),
+                self.map.food.len()
+            ),
+            ContentStyle::new().dark_grey(),
+        )
+            .draw((1, 2 + board.render_size().1), frame);
+    }
+}
+
+fn pick_size(
+    renderer: &mut Renderer,
+    frame: &mut impl CanvasLike,
+    title: &str,
+) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    let labels: Vec<_> = SIZES.iter().map(|size| size.to_string()).collect();
+    let options: Vec<_> = SIZES
+        .iter()
+        .zip(labels.iter())
+        .map(|(&size, label)| (size, label.as_str()))
+        .collect();
+    Ok(pick(renderer, frame, Color::Yellow, title, &options)?)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args().nth(1).unwrap_or_else(|| "map.txt".into());
+    // Loaded before the terminal is taken over so errors stay readable
+    let map = if Path::new(&path).exists() {
+        Map::load(&path).map_err(|err| format!("{}: {}", path, err))?
+    } else {
+        let mut map = Map::default();
+        map.spawns.truncate(2);
+        map
+    };
+    let mut editor = Editor {
+        map,
+        path,
+        cursor: (0, 0),
+        changed: false,
+    };
+
+    let mut renderer = Renderer::new()?;
+    let mut render_space = renderer.get_render_space();
+
+    loop {
+        Frame::new(&mut render_space).clear();
+        editor.draw(&mut render_space);
+        renderer.render()?;
+
+        let event = read()?;
+        let code = match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => break,
+            Event::Key(KeyEvent { code, kind, .. }) if !is_key_release(kind) => code,
+            _ => {
+                renderer.on_event(&event)?;
+                continue;
+            }
+        };
+
+        let result = match code {
+            KeyCode::Up | KeyCode::Char('w' | 'W') => editor.move_cursor(Dir::Up),
+            KeyCode::Down | KeyCode::Char('s' | 'S') => editor.move_cursor(Dir::Down),
+            KeyCode::Left | KeyCode::Char('a' | 'A') => editor.move_cursor(Dir::Left),
+            KeyCode::Right | KeyCode::Char('d' | 'D') => editor.move_cursor(Dir::Right),
+            KeyCode::Char(' ' | '#') => editor.toggle_wall(),
+            KeyCode::Char('*') => editor.toggle_food(),
+            KeyCode::Char(ch @ '1'..='8') => editor.place_spawn(ch as usize - '1' as usize),
+            KeyCode::Backspace | KeyCode::Delete | KeyCode::Char('.') => editor.clear(),
+            KeyCode::Esc => {
+                let Some(action) = pick(
+                    &mut renderer,
+                    &mut render_space,
+                    Color::Yellow,
+                    "Editor",
+                    &[
+                        (Action::Resume, "Back to editing"),
+                        (Action::Save, "Save"),
+                        (Action::Reload, "Reload from file"),
+                        (Action::Resize, "Resize"),
+                        (Action::Quit, "Quit"),
+                    ],
+                )?
+                else {
+                    continue;
+                };
+
+                match action {
+                    Action::Resume => Ok(()),
+                    Action::Save => match editor.save() {
+                        Ok(()) => {
+                            popup(
+                                &mut renderer,
+                                &mut render_space,
+                                ContentStyle::new().green(),
+                                ContentStyle::new(),
+                                "Saved",
+                                &[&editor.path],
+                            )
+                            .map_err(|err| err.0)?;
+                            Ok(())
+                        }
+                        err => err,
+                    },
+                    Action::Reload => editor.reload(),
+                    Action::Resize => {
+                        let Some(width) = pick_size(&mut renderer, &mut render_space, "Width")?
+                        else {
+                            continue;
+                        };
+                        let Some(height) = pick_size(&mut renderer, &mut render_space, "Height")?
+                        else {
+                            continue;
+                        };
+                        editor.resize(width, height);
+                        Ok(())
+                    }
+                    Action::Quit if !editor.changed => break,
+                    Action::Quit => match pick(
+                        &mut renderer,
+                        &mut render_space,
+                        Color::Yellow,
+                        "Quit without saving?",
+                        &[(false, "No"), (true, "Yes")],
+                    )? {
+                        Some(true) => break,
+                        _ => Ok(()),
+                    },
+                }
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(message) = result {
+            popup(
+                &mut renderer,
+                &mut render_space,
+                ContentStyle::new().red(),
+                ContentStyle::new(),
+                "Cannot do that",
+                &[&message],
+            )
+            .map_err(|err| err.0)?;
+        }
+    }
+
+    Ok(())
+}
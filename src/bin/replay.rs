@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{ContentStyle, Stylize},
+};
+use double_snake::{helpers::is_key_release, replay::Replay};
+use terminal_renderer::{
+    drawable::{misc::RightAlignedStringExt, Drawable},
+    frame::Frame,
+    renderer::Renderer,
+};
+
+/// Playback speeds, multiples of the speed the round was played at
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1., 2., 4., 8., 16.];
+/// Index of the normal speed in `SPEEDS`
+const NORMAL_SPEED: usize = 2;
+/// Updates skipped by a seek with the left and right arrows
+const SEEK_STEP: usize = 10;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args().nth(1).ok_or("Usage: replay <file>")?;
+    let replay = Replay::load(&path).map_err(|err| format!("{}: {}", path, err))?;
+
+    let mut renderer = Renderer::new()?;
+    let mut render_space = renderer.get_render_space();
+
+    let mut tick = 0;
+    let mut board = replay.board_at(tick);
+    let mut paused = false;
+    let mut speed = NORMAL_SPEED;
+    let mut last_update = Instant::now();
+
+    loop {
+        let mut seek = None;
+        while event::poll(Duration::from_millis(10))? {
+            let event = event::read()?;
+            match event {
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => return Ok(()),
+                Event::Key(KeyEvent { code, kind, .. }) if !is_key_release(kind) => match code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'Q') => return Ok(()),
+                    KeyCode::Char(' ') => paused = !paused,
+                    KeyCode::Char('.') => {
+                        paused = true;
+                        seek = Some(tick + 1);
+                    }
+                    KeyCode::Char(',') => {
+                        paused = true;
+                        seek = Some(tick.saturating_sub(1));
+                    }
+                    KeyCode::Right => seek = Some(tick + SEEK_STEP),
+                    KeyCode::Left => seek = Some(tick.saturating_sub(SEEK_STEP)),
+                    KeyCode::Home => seek = Some(0),
+                    KeyCode::End => seek = Some(replay.len()),
+                    KeyCode::Up => speed = (speed + 1).min(SPEEDS.len() - 1),
+                    KeyCode::Down => speed = speed.saturating_sub(1),
+                    _ => {}
+                },
+                _ => {}
+            }
+            renderer.on_event(&event)?;
+        }
+
+        if let Some(target) = seek {
+            let target = target.min(replay.len());
+            if target == tick + 1 {
+                board.update(replay.inputs(tick));
+            } else if target != tick {
+                // The board can't go back, so it's played again from the start
+                board = replay.board_at(target);
+            }
+            tick = target;
+            last_update = Instant::now();
+        } else if !paused
+            && tick < replay.len()
+            && last_update.elapsed() >= board.update_interval().div_f64(SPEEDS[speed])
+        {
+            board.update(replay.inputs(tick));
+            tick += 1;
+            last_update = Instant::now();
+        }
+
+        Frame::new(&mut render_space).clear();
+        "Space: pause, ,/.: step, Left/Right: seek, Up/Down: speed, Home/End: jump, Q: quit"
+            .draw((1, 0), &mut render_space);
+        format!("Tick {}/{}", tick, replay.len()).draw((1, 1), &mut render_space);
+        (
+            format!("{}x{}", if paused { "Paused, " } else { "" }, SPEEDS[speed]).right(),
+            ContentStyle::new(),
+        )
+            .draw(
+                (1, 1),
+                &mut Frame::new(&mut render_space).l(board.render_size().0).mx(1),
+            );
+        board.draw((0, 2), &mut render_space);
+        (
+            format!("Seed: {}", board.seed()),
+            ContentStyle::new().dark_grey(),
+        )
+            .draw((1, 2 + board.render_size().1), &mut render_space);
+
+        renderer.render()?;
+    }
+}
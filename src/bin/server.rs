@@ -0,0 +1,382 @@
+use std::{
+    error::Error,
+    io::{self, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo},
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{Clear, ClearType},
+};
+use double_snake::{
+    ansi,
+    board::{Board, BoardConfig, BoardState, Dir, EdgeMode, PlayerId, MAX_PLAYERS},
+    controller::{KeyboardController, SnakeController},
+    map::Map,
+    net::spectate::SpectatorHub,
+};
+
+const HELP: &str = "\
+Serves the game to remote terminals, every connection is a player
+
+Usage: server [options]
+
+Options:
+  --addr ADDR      Address to listen on [default: 0.0.0.0:7777]
+  --players N      Players in a match, from 1 to 8 [default: 2]
+  --map FILE       Arena to play in [default: empty 8x8]
+  --edges MODE     wrap or wall [default: wrap]
+  --spectate ADDR  Lets anyone watch on this TCP address, or on a local
+                   socket with unix:PATH
+
+Players connect with `telnet HOST PORT`. With nc the terminal has to send
+keys right away, `stty raw -echo; nc HOST PORT; stty sane` does that.
+They steer with WASD or the arrow keys, R starts the next round and Q
+leaves.";
+
+/// How often the screens are refreshed while seats are empty
+const WAITING_INTERVAL: Duration = Duration::from_millis(200);
+/// Clients that can't take a frame in this time are dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Telnet commands, see RFC 854
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+/// Telnet options, RFC 857 and RFC 858
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+
+struct Options {
+    addr: String,
+    players: usize,
+    map: Map,
+    edge_mode: EdgeMode,
+    spectate: Option<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut options = Options {
+            addr: "0.0.0.0:7777".into(),
+            players: 2,
+            map: Map::default(),
+            edge_mode: EdgeMode::Wrap,
+            spectate: None,
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "--help" || arg == "-h" {
+                println!("{}", HELP);
+                std::process::exit(0);
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value of {}", arg))?;
+            match arg.as_str() {
+                "--addr" => options.addr = value,
+                "--players" => options.players = value.parse()?,
+                "--map" => {
+                    options.map = Map::load(&value).map_err(|err| format!("{}: {}", value, err))?
+                }
+                "--edges" => {
+                    options.edge_mode = match value.as_str() {
+                        "wrap" => EdgeMode::Wrap,
+                        "wall" => EdgeMode::Wall,
+                        _ => return Err(format!("Unknown edge mode '{}'", value).into()),
+                    }
+                }
+                "--spectate" => options.spectate = Some(value),
+                _ => return Err(format!("Unknown option '{}'\n\n{}", arg, HELP).into()),
+            }
+        }
+
+        if options.players == 0 || options.players > MAX_PLAYERS {
+            return Err(format!("Players must be from 1 to {}", MAX_PLAYERS).into());
+        }
+        if options.players > options.map.spawns.len() {
+            return Err(format!(
+                "Map has spawns for only {} players",
+                options.map.spawns.len()
+            )
+            .into());
+        }
+        Ok(options)
+    }
+}
+
+enum ServerEvent {
+    Connected(usize, TcpStream),
+    Key(usize, KeyCode),
+    Left(usize),
+}
+
+struct Client {
+    id: usize,
+    stream: TcpStream,
+    player: PlayerId,
+    controller: KeyboardController,
+}
+
+impl Client {
+    fn new(id: usize, stream: TcpStream, player: PlayerId) -> Self {
+        Self {
+            id,
+            stream,
+            player,
+            controller: KeyboardController::new(
+                wasd_keys().into_iter().chain(arrow_keys()).collect(),
+            ),
+        }
+    }
+}
+
+fn wasd_keys() -> Vec<(KeyCode, Dir)> {
+    [
+        ('w', Dir::Up),
+        ('a', Dir::Left),
+        ('s', Dir::Down),
+        ('d', Dir::Right),
+    ]
+    .into_iter()
+    .flat_map(|(ch, dir)| {
+        [
+            (KeyCode::Char(ch), dir),
+            (KeyCode::Char(ch.to_ascii_uppercase()), dir),
+        ]
+    })
+    .collect()
+}
+
+fn arrow_keys() -> Vec<(KeyCode, Dir)> {
+    vec![
+        (KeyCode::Up, Dir::Up),
+        (KeyCode::Left, Dir::Left),
+        (KeyCode::Down, Dir::Down),
+        (KeyCode::Right, Dir::Right),
+    ]
+}
+
+/// Turns the bytes a telnet client or a raw terminal sends into keys,
+/// skipping telnet negotiation
+#[derive(Default)]
+struct KeyParser {
+    state: ParserState,
+}
+
+#[derive(Default)]
+enum ParserState {
+    #[default]
+    Text,
+    /// After IAC
+    Command,
+    /// After IAC and WILL, WONT, DO or DONT, the option is next
+    Option,
+    /// Inside IAC SB ... IAC SE
+    Subnegotiation { iac: bool },
+    /// After ESC
+    Escape,
+    /// After ESC [
+    Csi,
+}
+
+impl KeyParser {
+    fn feed(&mut self, byte: u8) -> Option<KeyCode> {
+        let (state, key) = match (&self.state, byte) {
+            (ParserState::Text, IAC) => (ParserState::Command, None),
+            (ParserState::Text, 0x1b) => (ParserState::Escape, None),
+            (ParserState::Text, byte) if byte.is_ascii_graphic() => {
+                (ParserState::Text, Some(KeyCode::Char(byte as char)))
+            }
+            (ParserState::Text, _) => (ParserState::Text, None),
+            (ParserState::Command, SB) => (ParserState::Subnegotiation { iac: false }, None),
+            (ParserState::Command, 251..=254) => (ParserState::Option, None),
+            (ParserState::Command, _) | (ParserState::Option, _) => (ParserState::Text, None),
+            (ParserState::Subnegotiation { iac: true }, SE) => (ParserState::Text, None),
+            (ParserState::Subnegotiation { .. }, byte) => {
+                (ParserState::Subnegotiation { iac: byte == IAC }, None)
+            }
+            (ParserState::Escape, b'[' | b'O') => (ParserState::Csi, None),
+            (ParserState::Escape, _) => (ParserState::Text, None),
+            (ParserState::Csi, byte) => (
+                ParserState::Text,
+                match byte {
+                    b'A' => Some(KeyCode::Up),
+                    b'B' => Some(KeyCode::Down),
+                    b'C' => Some(KeyCode::Right),
+                    b'D' => Some(KeyCode::Left),
+                    _ => None,
+                },
+            ),
+        };
+        self.state = state;
+        key
+    }
+}
+
+/// Accepts connections forever, every one gets a thread reading its keys
+fn accept(listener: TcpListener, events: Sender<ServerEvent>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else { continue };
+        let Ok(mut reader) = stream.try_clone() else {
+            continue;
+        };
+        if events.send(ServerEvent::Connected(id, stream)).is_err() {
+            break;
+        }
+
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut parser = KeyParser::default();
+            let mut buf = [0; 64];
+            while let Ok(len @ 1..) = reader.read(&mut buf) {
+                for key in buf[..len].iter().filter_map(|&byte| parser.feed(byte)) {
+                    let _ = events.send(ServerEvent::Key(id, key));
+                }
+            }
+            let _ = events.send(ServerEvent::Left(id));
+        });
+    }
+}
+
+/// Asks telnet clients to send keys right away without echoing them, and
+/// clears the screen
+fn welcome(stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.write_all(&[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD])?;
+    let mut out = Vec::new();
+    queue!(out, Hide, Clear(ClearType::All))?;
+    stream.write_all(&out)
+}
+
+/// Draws everything a client sees, sent in one write so frames don't tear
+fn draw(client: &mut Client, board: &Board, status: &str) -> io::Result<()> {
+    let mut out = Vec::new();
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        PrintStyledContent(
+            format!("You are P{}", client.player + 1).with(board.player_color(client.player))
+        ),
+        Print("  WASD/arrows: steer, R: next round, Q: leave"),
+    )?;
+    ansi::draw_match(board, board.cell_looks(), 1, status, &mut out)?;
+    client.stream.write_all(&out)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    let listener =
+        TcpListener::bind(&options.addr).map_err(|err| format!("{}: {}", options.addr, err))?;
+    println!("Listening on {}", listener.local_addr()?);
+
+    let spectators = match &options.spectate {
+        Some(addr) => {
+            let hub = SpectatorHub::listen(addr).map_err(|err| format!("{}: {}", addr, err))?;
+            println!("Spectators can watch on {}", hub.addr());
+            Some(hub)
+        }
+        None => None,
+    };
+
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || accept(listener, sender));
+
+    let mut board = Board::with_config(BoardConfig {
+        map: options.map.clone(),
+        players: options.players,
+        edge_mode: options.edge_mode,
+        ..Default::default()
+    });
+    let mut clients: Vec<Client> = Vec::new();
+
+    loop {
+        let full = clients.len() == options.players;
+        thread::sleep(if full {
+            board.update_interval()
+        } else {
+            WAITING_INTERVAL
+        });
+
+        let mut confirm = false;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                ServerEvent::Connected(id, mut stream) => {
+                    let seat =
+                        (0..options.players).find(|&p| !clients.iter().any(|c| c.player == p));
+                    match seat {
+                        Some(player) if welcome(&mut stream).is_ok() => {
+                            println!("P{} joined", player + 1);
+                            clients.push(Client::new(id, stream, player));
+                        }
+                        Some(_) => {}
+                        None => {
+                            let _ = stream.write_all(b"The match is full\r\n");
+                            let _ = stream.shutdown(Shutdown::Both);
+                        }
+                    }
+                }
+                ServerEvent::Key(id, key) => {
+                    let Some(client) = clients.iter_mut().find(|c| c.id == id) else {
+                        continue;
+                    };
+                    match key {
+                        KeyCode::Char('r' | 'R') => confirm = true,
+                        KeyCode::Char('q' | 'Q') => {
+                            let _ = client.stream.shutdown(Shutdown::Both);
+                        }
+                        key => client
+                            .controller
+                            .on_event(&Event::Key(KeyEvent::new(key, KeyModifiers::NONE))),
+                    }
+                }
+                ServerEvent::Left(id) => {
+                    if let Some(client) = clients.iter().find(|c| c.id == id) {
+                        println!("P{} left", client.player + 1);
+                    }
+                    clients.retain(|c| c.id != id);
+                }
+            }
+        }
+
+        // The board stands still while a seat is empty, whoever takes it next
+        // gets the snake where it stopped
+        if board.get_state() != BoardState::Running && confirm {
+            board.reset();
+            for client in clients.iter_mut() {
+                client.controller.reset();
+            }
+        } else if clients.len() == options.players {
+            let mut dirs = vec![None; options.players];
+            for client in clients.iter_mut() {
+                dirs[client.player] = client.controller.next_dir(&board, client.player);
+            }
+            board.update(&dirs);
+        }
+
+        let status = if clients.len() < options.players {
+            format!(
+                "Waiting for players, {} of {} here",
+                clients.len(),
+                options.players
+            )
+        } else {
+            ansi::round_result(board.get_state())
+                .map(|result| format!("{}, R for the next round", result))
+                .unwrap_or_default()
+        };
+        clients.retain_mut(|client| draw(client, &board, &status).is_ok());
+        if let Some(spectators) = &spectators {
+            spectators.broadcast(ansi::spectator_frame(&board, board.cell_looks(), &status));
+        }
+    }
+}
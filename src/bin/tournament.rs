@@ -0,0 +1,408 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use double_snake::{
+    board::{Board, BoardConfig, BoardState},
+    bot::{external::ExternalBot, greedy::GreedyBot, search::SearchBot, Difficulty},
+    controller::SnakeController,
+};
+
+const HELP: &str = "\
+Plays bots against each other without drawing anything
+
+Usage: tournament [options]
+
+Options:
+  --bots LIST      Bots taking part, from easy, normal, hard, expert
+                   [default: easy,normal,hard,expert]
+  --format FORMAT  round-robin or swiss [default: round-robin]
+  --rounds N       Rounds of a swiss tournament [default: 3]
+  --games N        Games per pairing, sides swap every game [default: 100]
+  --seed N         Seed of the first game, the next ones count up [default: 0]
+  --external CMD   Adds a bot running as its own program, can be repeated
+  --depth N        Updates the expert bot looks ahead [default: 3]
+  --budget MS      Thinking time of the expert bot per update instead of
+                   --depth, its games then depend on the machine and the
+                   load and can't be played again from --seed
+  --timeout MS     Time external bots have to answer [default: 100]
+  --max-ticks N    Games running longer are draws [default: 5000]
+  --threads N      Games played at once [default: all cores]";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    RoundRobin,
+    Swiss,
+}
+
+#[derive(Clone)]
+enum BotKind {
+    Greedy(Difficulty),
+    Search,
+    /// Command starting the bot's program
+    External(String),
+}
+
+impl BotKind {
+    fn name(&self) -> &str {
+        match self {
+            BotKind::Greedy(Difficulty::Easy) => "easy",
+            BotKind::Greedy(Difficulty::Normal) => "normal",
+            BotKind::Greedy(Difficulty::Hard) => "hard",
+            BotKind::Search => "expert",
+            BotKind::External(command) => command,
+        }
+    }
+}
+
+impl FromStr for BotKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(BotKind::Greedy(Difficulty::Easy)),
+            "normal" => Ok(BotKind::Greedy(Difficulty::Normal)),
+            "hard" => Ok(BotKind::Greedy(Difficulty::Hard)),
+            "expert" => Ok(BotKind::Search),
+            _ => Err(format!("Unknown bot '{}'", s)),
+        }
+    }
+}
+
+struct Options {
+    bots: Vec<BotKind>,
+    format: Format,
+    rounds: usize,
+    games: usize,
+    seed: u64,
+    depth: usize,
+    budget: Option<Duration>,
+    timeout: Duration,
+    max_ticks: u32,
+    threads: usize,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut options = Options {
+            bots: ["easy", "normal", "hard", "expert"]
+                .iter()
+                .map(|name| name.parse().unwrap())
+                .collect(),
+            format: Format::RoundRobin,
+            rounds: 3,
+            games: 100,
+            seed: 0,
+            depth: 3,
+            budget: None,
+            timeout: Duration::from_millis(100),
+            max_ticks: 5000,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        // Added after the others, so `--bots` doesn't drop them
+        let mut externals = Vec::new();
+
+        while let Some(arg) = args.next() {
+            if arg == "--help" || arg == "-h" {
+                println!("{}", HELP);
+                std::process::exit(0);
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value of {}", arg))?;
+            match arg.as_str() {
+                "--bots" => {
+                    options.bots = value.split(',').map(str::parse).collect::<Result<_, _>>()?
+                }
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "round-robin" => Format::RoundRobin,
+                        "swiss" => Format::Swiss,
+                        _ => return Err(format!("Unknown format '{}'", value).into()),
+                    }
+                }
+                "--rounds" => options.rounds = value.parse()?,
+                "--games" => options.games = value.parse()?,
+                "--seed" => options.seed = value.parse()?,
+                "--external" => externals.push(value),
+                "--depth" => options.depth = value.parse()?,
+                "--budget" => options.budget = Some(Duration::from_millis(value.parse()?)),
+                "--timeout" => options.timeout = Duration::from_millis(value.parse()?),
+                "--max-ticks" => options.max_ticks = value.parse()?,
+                "--threads" => options.threads = value.parse::<usize>()?.max(1),
+                _ => return Err(format!("Unknown option '{}'\n\n{}", arg, HELP).into()),
+            }
+        }
+
+        // Started once up front, a wrong command should fail here and not in
+        // the middle of the tournament
+        for command in externals.iter() {
+            ExternalBot::spawn(command, options.timeout)
+                .map_err(|err| format!("{}: {}", command, err))?;
+        }
+        options
+            .bots
+            .extend(externals.into_iter().map(BotKind::External));
+        if options.bots.len() < 2 {
+            return Err("At least 2 bots are needed".into());
+        }
+        Ok(options)
+    }
+
+    fn controller(&self, kind: &BotKind, seed: u64) -> Box<dyn SnakeController> {
+        match kind {
+            BotKind::Greedy(difficulty) => Box::new(GreedyBot::new(*difficulty, seed)),
+            BotKind::Search => Box::new(match self.budget {
+                Some(budget) => SearchBot::new(budget),
+                None => SearchBot::with_depth(self.depth),
+            }),
+            BotKind::External(command) => Box::new(
+                ExternalBot::spawn(command, self.timeout)
+                    .unwrap_or_else(|err| panic!("Cannot start '{}': {}", command, err)),
+            ),
+        }
+    }
+}
+
+/// One game between two entrants, indexes into `Options::bots`
+#[derive(Clone, Copy)]
+struct Game {
+    players: [usize; 2],
+    seed: u64,
+}
+
+struct GameResult {
+    game: Game,
+    /// Entrant that won, `None` on a draw
+    winner: Option<usize>,
+    lengths: [usize; 2],
+    ticks: u32,
+}
+
+#[derive(Default)]
+struct Stats {
+    games: usize,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    total_length: usize,
+    total_ticks: u64,
+    /// Swiss points, 2 for a won match and 1 for a drawn one
+    points: usize,
+}
+
+fn play(options: &Options, game: Game) -> GameResult {
+    let mut board = Board::with_config(BoardConfig {
+        seed: Some(game.seed),
+        ..Default::default()
+    });
+    let mut controllers: Vec<_> = game
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, &entrant)| options.controller(&options.bots[entrant], game.seed + i as u64))
+        .collect();
+
+    while board.get_state() == BoardState::Running && board.ticks() < options.max_ticks {
+        let dirs: Vec<_> = controllers
+            .iter_mut()
+            .enumerate()
+            .map(|(player, controller)| controller.next_dir(&board, player))
+            .collect();
+        board.update(&dirs);
+    }
+
+    let scores = board.get_scores();
+    GameResult {
+        game,
+        winner: match board.get_state() {
+            BoardState::Won { player } => Some(game.players[player]),
+            BoardState::Filled { players } if players.len() == 1 => {
+                players.iter().next().map(|player| game.players[player])
+            }
+            _ => None,
+        },
+        lengths: [scores[0], scores[1]],
+        ticks: board.ticks(),
+    }
+}
+
+/// Plays all games on `options.threads` threads, results come in the order
+/// the games finish
+fn play_all(options: &Options, games: &[Game]) -> Vec<GameResult> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..options.threads.min(games.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                while let Some(&game) = games.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    sender.send(play(options, game)).unwrap();
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    receiver.into_iter().collect()
+}
+
+/// Games between two entrants, sides swap every game so neither gets the
+/// better spawn more often
+fn match_games(players: [usize; 2], options: &Options, seed: &mut u64) -> Vec<Game> {
+    (0..options.games)
+        .map(|i| {
+            *seed += 1;
+            Game {
+                players: if i % 2 == 0 {
+                    players
+                } else {
+                    [players[1], players[0]]
+                },
+                seed: *seed - 1,
+            }
+        })
+        .collect()
+}
+
+fn record(stats: &mut [Stats], results: &[GameResult]) {
+    for result in results {
+        for (side, &entrant) in result.game.players.iter().enumerate() {
+            let entry = &mut stats[entrant];
+            entry.games += 1;
+            entry.total_length += result.lengths[side];
+            entry.total_ticks += result.ticks as u64;
+            match result.winner {
+                Some(winner) if winner == entrant => entry.wins += 1,
+                Some(_) => entry.losses += 1,
+                None => entry.draws += 1,
+            }
+        }
+    }
+}
+
+/// Pairs entrants with similar points who haven't met yet, the one left
+/// over with an odd count gets a bye worth a won match
+fn swiss_pairings(stats: &mut [Stats], played: &BTreeSet<(usize, usize)>) -> Vec<[usize; 2]> {
+    let mut order: Vec<_> = (0..stats.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(stats[i].points));
+
+    let mut pairs = Vec::new();
+    while let Some(first) = order.first().copied() {
+        order.remove(0);
+        if order.is_empty() {
+            stats[first].points += 2;
+            break;
+        }
+        let met = |other: usize| played.contains(&(first.min(other), first.max(other)));
+        let second = order.iter().position(|&other| !met(other)).unwrap_or(0);
+        pairs.push([first, order.remove(second)]);
+    }
+    pairs
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    let mut stats: Vec<_> = options.bots.iter().map(|_| Stats::default()).collect();
+    let mut seed = options.seed;
+
+    match options.format {
+        Format::RoundRobin => {
+            let games: Vec<_> = (0..options.bots.len())
+                .flat_map(|a| (a + 1..options.bots.len()).map(move |b| [a, b]))
+                .flat_map(|pair| match_games(pair, &options, &mut seed))
+                .collect();
+            record(&mut stats, &play_all(&options, &games));
+        }
+        Format::Swiss => {
+            let mut played = BTreeSet::new();
+            for round in 1..=options.rounds {
+                let pairs = swiss_pairings(&mut stats, &played);
+                let games: Vec<_> = pairs
+                    .iter()
+                    .flat_map(|&pair| match_games(pair, &options, &mut seed))
+                    .collect();
+                let results = play_all(&options, &games);
+                record(&mut stats, &results);
+
+                println!("Round {}", round);
+                for [a, b] in pairs {
+                    played.insert((a.min(b), a.max(b)));
+                    let wins = |entrant| {
+                        results
+                            .iter()
+                            .filter(|r| r.winner == Some(entrant))
+                            .filter(|r| r.game.players.contains(&a) && r.game.players.contains(&b))
+                            .count()
+                    };
+                    let (a_wins, b_wins) = (wins(a), wins(b));
+                    match a_wins.cmp(&b_wins) {
+                        std::cmp::Ordering::Greater => stats[a].points += 2,
+                        std::cmp::Ordering::Less => stats[b].points += 2,
+                        std::cmp::Ordering::Equal => {
+                            stats[a].points += 1;
+                            stats[b].points += 1;
+                        }
+                    }
+                    println!(
+                        "  {:>8} {:>4} : {:<4} {}",
+                        options.bots[a].name(),
+                        a_wins,
+                        b_wins,
+                        options.bots[b].name()
+                    );
+                }
+            }
+            println!();
+        }
+    }
+
+    let mut order: Vec<_> = (0..stats.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((stats[i].points, stats[i].wins)));
+
+    let percent = |count: usize, total: usize| 100. * count as f64 / total.max(1) as f64;
+    println!(
+        "{:>8} {:>6} {:>7} {:>7} {:>7} {:>7} {:>9}{}",
+        "Bot",
+        "Games",
+        "Wins",
+        "Losses",
+        "Draws",
+        "Length",
+        "Ticks",
+        if options.format == Format::Swiss {
+            "  Points"
+        } else {
+            ""
+        }
+    );
+    for i in order {
+        let s = &stats[i];
+        println!(
+            "{:>8} {:>6} {:>6.1}% {:>6.1}% {:>6.1}% {:>7.2} {:>9.1}{}",
+            options.bots[i].name(),
+            s.games,
+            percent(s.wins, s.games),
+            percent(s.losses, s.games),
+            percent(s.draws, s.games),
+            s.total_length as f64 / s.games.max(1) as f64,
+            s.total_ticks as f64 / s.games.max(1) as f64,
+            if options.format == Format::Swiss {
+                format!(" {:>7.1}", s.points as f64 / 2.)
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    Ok(())
+}
@@ -1,104 +1,1024 @@
-use std::time::Duration;
+use std::{collections::VecDeque, fmt, fs, io, path::Path, time::Duration};
 
 use crossterm::style::{Color, ContentStyle, Stylize};
-use rand::{seq::SliceRandom, thread_rng};
-use terminal_renderer::{
-    canvas::CanvasLike, cell::Cell, drawable::Drawable, frame::Frame, renderer::Dims,
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    thread_rng, Rng, SeedableRng,
 };
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "render")]
+use terminal_renderer::{canvas::CanvasLike, cell::Cell, drawable::Drawable, frame::Frame};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// Index of a player, also index of their snake on the board
+pub type PlayerId = usize;
+
+/// Position of a cell or a size, in cells
+pub type Dims = (i32, i32);
+
+pub const MAX_PLAYERS: usize = 8;
+
+/// How many turns a snake remembers ahead, extra ones are dropped
+pub const TURN_QUEUE_LEN: usize = 3;
+
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::Green,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::DarkGreen,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+];
+
+/// Set of players sharing a result, saved as a list of players
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<PlayerId>", try_from = "Vec<PlayerId>")]
+pub struct PlayerSet(u8);
+
+impl PlayerSet {
+    pub fn insert(&mut self, player: PlayerId) {
+        self.0 |= 1 << player;
+    }
+
+    pub fn contains(&self, player: PlayerId) -> bool {
+        self.0 & (1 << player) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        (0..MAX_PLAYERS).filter(|&player| self.contains(player))
+    }
+}
+
+impl From<PlayerSet> for Vec<PlayerId> {
+    fn from(set: PlayerSet) -> Self {
+        set.iter().collect()
+    }
+}
+
+impl TryFrom<Vec<PlayerId>> for PlayerSet {
+    type Error = String;
+
+    fn try_from(players: Vec<PlayerId>) -> Result<Self, Self::Error> {
+        match players.iter().find(|&&player| player >= MAX_PLAYERS) {
+            Some(player) => Err(format!("player {} doesn't exist", player)),
+            None => Ok(players.into_iter().collect()),
+        }
+    }
+}
+
+impl FromIterator<PlayerId> for PlayerSet {
+    fn from_iter<I: IntoIterator<Item = PlayerId>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for player in iter {
+            set.insert(player);
+        }
+        set
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
 pub enum BoardState {
     #[default]
     Running,
     Won {
-        is_player2: bool,
+        player: PlayerId,
+    },
+    /// Round ended without a single winner, these players share it
+    Draw {
+        players: PlayerSet,
+    },
+    /// Nobody is left, only happens when playing alone
+    AllLost,
+    /// No free cell is left for food, the longest snakes win, several of
+    /// them means a draw
+    Filled {
+        players: PlayerSet,
     },
-    BothLost,
 }
 
-use crate::{
-    helpers::value_if_else,
-    ui::{dims::IntoUidims, uibox::UiBox},
-};
+/// Why a snake died
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Wall,
+    OwnBody,
+    /// Ran into the body of another snake
+    Body {
+        owner: PlayerId,
+    },
+    /// Met the head of another snake, in one cell or by swapping cells
+    HeadOn {
+        other: PlayerId,
+    },
+}
+
+/// Something that happened during [`Board::update`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    Moved {
+        player: PlayerId,
+        from: Dims,
+        to: Dims,
+    },
+    FoodEaten {
+        player: PlayerId,
+        pos: Dims,
+        kind: FoodKind,
+    },
+    FoodSpawned {
+        pos: Dims,
+        kind: FoodKind,
+    },
+    /// Temporary food disappeared before anyone ate it
+    FoodExpired {
+        pos: Dims,
+    },
+    Died {
+        player: PlayerId,
+        cause: DeathCause,
+    },
+    RoundEnded {
+        state: BoardState,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodKind {
+    Normal,
+    /// Grows the snake by [`FoodConfig::golden_growth`] segments
+    Golden,
+    /// Shrinks the snake by [`FoodConfig::poison_shrink`] segments
+    Poison,
+    /// Disappears after [`FoodConfig::temporary_ticks`] updates
+    Temporary,
+}
+
+impl FoodKind {
+    pub fn color(self) -> Color {
+        match self {
+            FoodKind::Normal => Color::Yellow,
+            FoodKind::Golden => Color::DarkYellow,
+            FoodKind::Poison => Color::Red,
+            FoodKind::Temporary => Color::DarkGrey,
+        }
+    }
+
+    /// Character of the kind in [`Board::to_ascii`]
+    fn symbol(self) -> char {
+        match self {
+            FoodKind::Normal => '*',
+            FoodKind::Golden => '$',
+            FoodKind::Poison => '!',
+            FoodKind::Temporary => '%',
+        }
+    }
+
+    fn from_symbol(ch: char) -> Option<Self> {
+        [
+            FoodKind::Normal,
+            FoodKind::Golden,
+            FoodKind::Poison,
+            FoodKind::Temporary,
+        ]
+        .into_iter()
+        .find(|kind| kind.symbol() == ch)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Food {
+    pub pos: Dims,
+    pub kind: FoodKind,
+    /// Updates left before it disappears
+    pub ttl: Option<u32>,
+}
+
+/// How much food there is and what kind, there is always at least one item
+/// while there's room for it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FoodConfig {
+    /// Most items on the board at once
+    pub max_items: usize,
+    /// Chance to spawn another item each update while there's less than
+    /// `max_items`
+    pub spawn_chance: f64,
+    /// Relative chance of each kind for new items
+    pub weights: Vec<(FoodKind, u32)>,
+    pub golden_growth: usize,
+    pub poison_shrink: usize,
+    pub temporary_ticks: u32,
+}
+
+impl FoodConfig {
+    /// Single normal food, like the original game
+    pub fn classic() -> Self {
+        Self {
+            max_items: 1,
+            spawn_chance: 0.0,
+            weights: vec![(FoodKind::Normal, 1)],
+            golden_growth: 3,
+            poison_shrink: 2,
+            temporary_ticks: 20,
+        }
+    }
+
+    pub fn mixed() -> Self {
+        Self {
+            max_items: 3,
+            spawn_chance: 0.1,
+            weights: vec![
+                (FoodKind::Normal, 6),
+                (FoodKind::Golden, 1),
+                (FoodKind::Poison, 1),
+                (FoodKind::Temporary, 2),
+            ],
+            ..Self::classic()
+        }
+    }
+
+    pub fn chaos() -> Self {
+        Self {
+            max_items: 8,
+            spawn_chance: 0.4,
+            weights: vec![
+                (FoodKind::Normal, 3),
+                (FoodKind::Golden, 2),
+                (FoodKind::Poison, 3),
+                (FoodKind::Temporary, 3),
+            ],
+            temporary_ticks: 10,
+            ..Self::classic()
+        }
+    }
+
+    /// Segments a snake grows by eating the food, negative shrinks it
+    fn growth(&self, kind: FoodKind) -> isize {
+        match kind {
+            FoodKind::Normal | FoodKind::Temporary => 1,
+            FoodKind::Golden => self.golden_growth as isize,
+            FoodKind::Poison => -(self.poison_shrink as isize),
+        }
+    }
+}
+
+impl Default for FoodConfig {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+/// What happens when a snake leaves the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeMode {
+    /// Snake comes out on the opposite side
+    #[default]
+    Wrap,
+    /// Edge is a solid wall, running into it is fatal
+    Wall,
+}
+
+/// Speed curve of a round, the interval between updates starts at `start`
+/// and gets shorter as the snakes grow and the time passes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Speed {
+    pub start: Duration,
+    /// Interval never gets shorter than this
+    pub min: Duration,
+    /// Taken off for every segment the longest snake has grown
+    pub per_segment: Duration,
+    /// Taken off every `ticks_per_step` updates, never when it's 0
+    pub per_step: Duration,
+    pub ticks_per_step: u32,
+}
+
+impl Speed {
+    /// Default curve starting at the given interval
+    pub fn starting_at(start: Duration) -> Self {
+        Self {
+            start,
+            min: start / 3,
+            per_segment: start / 60,
+            per_step: start / 30,
+            ticks_per_step: 50,
+        }
+    }
+
+    /// Same interval for the whole round
+    pub fn constant(interval: Duration) -> Self {
+        Self {
+            start: interval,
+            min: interval,
+            per_segment: Duration::ZERO,
+            per_step: Duration::ZERO,
+            ticks_per_step: 0,
+        }
+    }
+
+    fn interval(&self, growth: usize, ticks: u32) -> Duration {
+        let steps = ticks.checked_div(self.ticks_per_step).unwrap_or(0);
+        self.start
+            .saturating_sub(self.per_segment * growth as u32)
+            .saturating_sub(self.per_step * steps)
+            .max(self.min)
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self::starting_at(Duration::from_millis(300))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoardConfig {
+    /// Size, walls and spawn points of the arena
+    pub map: Map,
+    /// Number of snakes, from 1 to [`MAX_PLAYERS`]
+    pub players: usize,
+    pub edge_mode: EdgeMode,
+    /// Seed for food placement, random for every round when `None`
+    pub seed: Option<u64>,
+    pub speed: Speed,
+    pub food: FoodConfig,
+}
+
+impl BoardConfig {
+    /// Why no board can be made with the config, if there's a reason
+    ///
+    /// Everything that makes a board from a config it didn't build itself,
+    /// like a saved match or a replay, checks it first.
+    pub fn check(&self) -> Result<(), String> {
+        let map = &self.map;
+        if map.size.0 < 2 || map.size.1 < 2 {
+            return Err("Board must be at least 2x2".into());
+        }
+        if self.players == 0 || self.players > MAX_PLAYERS {
+            return Err(format!("Board must have 1 to {} players", MAX_PLAYERS));
+        }
+        if self.players > map.spawns.len() {
+            return Err(format!(
+                "Map has spawns for only {} players",
+                map.spawns.len()
+            ));
+        }
+        let spawns = &map.spawns[..self.players];
+        if !spawns.iter().all(|&pos| map.contains(pos)) {
+            return Err("Map has spawns off the board".into());
+        }
+        if (1..spawns.len()).any(|i| spawns[..i].contains(&spawns[i])) {
+            return Err(format!("Board is too small for {} players", self.players));
+        }
+        Ok(())
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self {
+            map: Map::default(),
+            players: 2,
+            edge_mode: EdgeMode::default(),
+            seed: None,
+            speed: Speed::default(),
+            food: FoodConfig::default(),
+        }
+    }
+}
+
+use crate::map::{Map, MapError};
+#[cfg(feature = "render")]
+use crate::ui::{dims::IntoUidims, uibox::UiBox};
+
+/// Whole state of a round, cloning it gives a copy that plays out the same
+/// way given the same inputs, which is how bots look ahead
+///
+/// Saving it with serde keeps everything that decides how the round goes
+/// on, the random number generator included, so a loaded board plays out
+/// the same way too. Only the history is left out.
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "BoardData")]
 pub struct Board {
-    snake1: Snake,
-    snake2: Snake,
-    food: Dims,
+    config: BoardConfig,
+    seed: u64,
+    rng: ChaCha8Rng,
+    snakes: Vec<Snake>,
+    food: Vec<Food>,
     state: BoardState,
+    ticks: u32,
+    /// Cells snakes died in on the last update
+    crashes: Vec<Dims>,
+    /// `None` unless turned on with [`Board::keep_history`]
+    #[serde(skip)]
+    history: Option<History>,
+}
+
+/// Why [`Board::from_ascii`] didn't take a diagram
+#[derive(Debug)]
+pub enum AsciiError {
+    /// Grid itself is broken, heads are checked as spawns of the map
+    Map(MapError),
+    NoSnakes,
+    /// Body segments of the player don't make one line starting at the head
+    BrokenBody {
+        player: PlayerId,
+    },
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiError::Map(err) => write!(f, "{}", err),
+            AsciiError::NoSnakes => write!(f, "Board has no snakes"),
+            AsciiError::BrokenBody { player } => {
+                write!(f, "Body of player {} doesn't follow its head", player + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsciiError {}
+
+impl From<MapError> for AsciiError {
+    fn from(error: MapError) -> Self {
+        Self::Map(error)
+    }
+}
+
+/// Saved board before it's checked to make sense
+#[derive(Deserialize)]
+struct BoardData {
+    config: BoardConfig,
+    seed: u64,
+    rng: ChaCha8Rng,
+    snakes: Vec<Snake>,
+    food: Vec<Food>,
+    state: BoardState,
+    ticks: u32,
+    crashes: Vec<Dims>,
+}
+
+impl TryFrom<BoardData> for Board {
+    type Error = String;
+
+    fn try_from(data: BoardData) -> Result<Self, Self::Error> {
+        // The next round starts from the config
+        data.config.check()?;
+        let map = &data.config.map;
+        let players = data.config.players;
+        if data.snakes.len() != players {
+            return Err(format!(
+                "{} snakes for {} players",
+                data.snakes.len(),
+                players
+            ));
+        }
+        for (player, snake) in data.snakes.iter().enumerate() {
+            if snake.player != player {
+                return Err(format!(
+                    "Snake {} belongs to player {}",
+                    player, snake.player
+                ));
+            }
+            if snake.body.is_empty() || !snake.body.iter().all(|&pos| map.contains(pos)) {
+                return Err(format!("Snake of player {} is off the board", player));
+            }
+        }
+        if !data.food.iter().all(|f| map.contains(f.pos)) {
+            return Err("Food is off the board".into());
+        }
+        if !data.crashes.iter().all(|&pos| map.contains(pos)) {
+            return Err("Crash is off the board".into());
+        }
+        let on_board = |player: PlayerId| player < players;
+        let result_fits = match data.state {
+            BoardState::Running | BoardState::AllLost => true,
+            BoardState::Won { player } => on_board(player),
+            BoardState::Draw { players } | BoardState::Filled { players } => {
+                !players.is_empty() && players.iter().all(on_board)
+            }
+        };
+        if !result_fits {
+            return Err("Result names players that aren't on the board".into());
+        }
+
+        Ok(Self {
+            config: data.config,
+            seed: data.seed,
+            rng: data.rng,
+            snakes: data.snakes,
+            food: data.food,
+            state: data.state,
+            ticks: data.ticks,
+            crashes: data.crashes,
+            history: None,
+        })
+    }
 }
 
-const TELEPORT_ON_EDGE: bool = true;
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "Cannot access saved match: {}", err),
+            SaveError::Format(err) => write!(f, "Broken saved match: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Format(error)
+    }
+}
+
+/// Rolling record of the last updates for the instant replay
+struct History {
+    /// How far back updates are kept
+    duration: Duration,
+    snapshots: VecDeque<Snapshot>,
+}
+
+/// Board right after one update
+struct Snapshot {
+    /// Living snakes and the ones that died on this update
+    snakes: Vec<Snake>,
+    food: Vec<Food>,
+    crashes: Vec<Dims>,
+    /// How long the update was shown for
+    interval: Duration,
+}
+
+/// One update of the instant replay, see [`Board::instant_replay`]
+pub struct ReplayFrame<'a> {
+    board: &'a Board,
+    snapshot: &'a Snapshot,
+}
+
+impl ReplayFrame<'_> {
+    /// Living snakes and the ones that died on this update
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snapshot.snakes
+    }
+
+    pub fn food(&self) -> &[Food] {
+        &self.snapshot.food
+    }
+
+    /// Cells snakes crashed into on this update
+    pub fn crashes(&self) -> &[Dims] {
+        &self.snapshot.crashes
+    }
+
+    /// How long the update was shown for while playing
+    pub fn interval(&self) -> Duration {
+        self.snapshot.interval
+    }
+
+    /// See [`Board::cell_looks`]
+    pub fn cell_looks(&self) -> Vec<Vec<(char, ContentStyle)>> {
+        self.board.looks(Some(self.snapshot))
+    }
+}
+
+impl Clone for Board {
+    /// The copy doesn't keep a history, bots clone boards far too often for
+    /// that
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            snakes: self.snakes.clone(),
+            food: self.food.clone(),
+            state: self.state,
+            ticks: self.ticks,
+            crashes: self.crashes.clone(),
+            history: None,
+        }
+    }
+}
 
 impl Board {
     pub fn new() -> Self {
-        let snakes = [
-            Snake::new((0, 0), Color::Green),
-            Snake::new((7, 7), Color::Blue),
-        ];
-        let [s1, s2] = snakes;
+        Self::with_config(BoardConfig::default())
+    }
 
+    pub fn new_with_seed(seed: u64) -> Self {
+        Self::with_config(BoardConfig {
+            seed: Some(seed),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_size(width: i32, height: i32) -> Self {
+        Self::with_config(BoardConfig {
+            map: Map::empty(width, height),
+            ..Default::default()
+        })
+    }
+
+    /// Panics when the config fails [`BoardConfig::check`]
+    pub fn with_config(config: BoardConfig) -> Self {
+        if let Err(err) = config.check() {
+            panic!("{}", err);
+        }
+
+        let snakes: Vec<_> = config.map.spawns[..config.players]
+            .iter()
+            .enumerate()
+            .map(|(player, &pos)| Snake::new(player, pos, spawn_dir(&config.map, pos)))
+            .collect();
+
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
         let mut board = Self {
-            snake1: s1,
-            snake2: s2,
-            food: (0, 0),
+            config,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            snakes,
+            food: Vec::new(),
             state: BoardState::default(),
+            ticks: 0,
+            crashes: Vec::new(),
+            history: None,
         };
-        board.move_food();
+        board.food = starting_food(&board.config.map);
+        if board.food.is_empty() && board.spawn_food().is_none() {
+            board.state = board.filled_state();
+        }
 
         board
     }
 
-    pub fn update(&mut self, snake1_dir: Option<Dir>, snake2_dir: Option<Dir>) {
+    /// Board showing the map exactly as a round starts on it, with a snake on
+    /// every spawn and no random food, meant only for drawing
+    pub fn preview(map: Map) -> Self {
+        let snakes = map
+            .spawns
+            .iter()
+            .enumerate()
+            .map(|(player, &pos)| Snake::new(player, pos, spawn_dir(&map, pos)))
+            .collect();
+        let food = starting_food(&map);
+        Self {
+            config: BoardConfig {
+                players: map.spawns.len(),
+                map,
+                seed: Some(0),
+                ..Default::default()
+            },
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            snakes,
+            food,
+            state: BoardState::Running,
+            ticks: 0,
+            crashes: Vec::new(),
+            history: None,
+        }
+    }
+
+    /// Queues a turn for the player's snake, it's used on one of the next
+    /// updates, one turn per update
+    ///
+    /// Returns `false` if the turn was rejected because it would do nothing,
+    /// reverse the snake into its own neck or the queue is full.
+    pub fn queue_turn(&mut self, player: PlayerId, dir: Dir) -> bool {
+        if !self.changes_course(player, dir) {
+            return false;
+        }
+        let snake = &mut self.snakes[player];
+        if !snake.alive || snake.turns.len() >= TURN_QUEUE_LEN {
+            return false;
+        }
+
+        snake.turns.push_back(dir);
+        true
+    }
+
+    /// Whether turning to `dir` after the queued turns would change where
+    /// the player's snake goes, it doesn't when the snake already goes that
+    /// way or it would reverse into its neck
+    pub fn changes_course(&self, player: PlayerId, dir: Dir) -> bool {
+        let snake = &self.snakes[player];
+        let last = snake.turns.back().copied().unwrap_or(snake.dir);
+        dir != last && (dir != last.opposite() || snake.body.len() == 1)
+    }
+
+    /// Moves every living snake, `dirs` are indexed by player and are queued
+    /// before moving, see [`Board::queue_turn`]
+    ///
+    /// All moves happen at the same time, no snake gets to go first. Heads
+    /// meeting in one cell or swapping cells kill both snakes, a cell freed
+    /// by a moving tail is safe to enter.
+    ///
+    /// Returns everything that happened, in the order it happened.
+    pub fn update(&mut self, dirs: &[Option<Dir>]) -> Vec<GameEvent> {
         if self.state != BoardState::Running {
-            return;
+            return Vec::new();
         }
 
-        self.snake1.go(snake1_dir);
-        self.snake2.go(snake2_dir);
+        self.ticks += 1;
+        for (player, dir) in dirs.iter().enumerate() {
+            if let Some(dir) = *dir {
+                self.queue_turn(player, dir);
+            }
+        }
 
-        if self.snake1.pos() == self.food {
-            self.snake1.body.push(self.snake1.last_end);
-            self.move_food();
+        for snake in self.snakes.iter_mut().filter(|s| s.alive) {
+            snake.turn();
         }
 
-        if self.snake2.pos() == self.food {
-            self.snake2.body.push(self.snake2.last_end);
-            self.move_food();
+        let heads: Vec<_> = self
+            .snakes
+            .iter()
+            .map(|s| s.alive.then(|| self.next_pos(s.head(), s.dir)).flatten())
+            .collect();
+        let eats: Vec<_> = heads
+            .iter()
+            .map(|&head| self.food.iter().position(|f| Some(f.pos) == head))
+            .collect();
+        // Whether the tail stays in place this update
+        let grows: Vec<_> = self
+            .snakes
+            .iter()
+            .map(|s| {
+                s.growth > 0
+                    || eats[s.player]
+                        .is_some_and(|i| self.config.food.growth(self.food[i].kind) > 0)
+            })
+            .collect();
+        let mut events = Vec::new();
+        let mut dead = PlayerSet::default();
+        let mut crashes = Vec::new();
+        for snake in self.living_snakes() {
+            if let Some(cause) = self.death_cause(snake, &heads, &grows) {
+                dead.insert(snake.player);
+                // Running into a wall or the edge leaves the head in place
+                let crash = heads[snake.player].unwrap_or_else(|| {
+                    let next = snake.dir.step(snake.head());
+                    if self.config.map.contains(next) {
+                        next
+                    } else {
+                        snake.head()
+                    }
+                });
+                if !crashes.contains(&crash) {
+                    crashes.push(crash);
+                }
+                events.push(GameEvent::Died {
+                    player: snake.player,
+                    cause,
+                });
+            }
         }
 
-        if self.snake1.pos() == self.snake2.pos() {
-            self.state = BoardState::BothLost;
-            return;
+        self.crashes = crashes;
+
+        let mut eaten = Vec::new();
+        for snake in self.snakes.iter_mut().filter(|s| s.alive) {
+            if dead.contains(snake.player) {
+                snake.alive = false;
+            } else if let Some(head) = heads[snake.player] {
+                events.push(GameEvent::Moved {
+                    player: snake.player,
+                    from: snake.head(),
+                    to: head,
+                });
+
+                let food = eats[snake.player].map(|i| self.food[i]);
+                let growth = food.map_or(0, |f| self.config.food.growth(f.kind));
+                snake.growth += growth.max(0) as usize;
+                snake.advance(head);
+                snake.shrink((-growth).max(0) as usize);
+
+                if let Some(food) = food {
+                    eaten.push(food.pos);
+                    events.push(GameEvent::FoodEaten {
+                        player: snake.player,
+                        pos: head,
+                        kind: food.kind,
+                    });
+                }
+            }
         }
 
-        if self.snake2.body.contains(&self.snake1.pos())
-            || self.snake1.tail().contains(&self.snake1.pos())
-        {
-            self.state = BoardState::Won { is_player2: true };
-            return;
+        self.food.retain(|f| !eaten.contains(&f.pos));
+        for food in self.food.iter_mut() {
+            food.ttl = food.ttl.map(|ttl| ttl.saturating_sub(1));
+            if food.ttl == Some(0) {
+                events.push(GameEvent::FoodExpired { pos: food.pos });
+            }
         }
+        self.food.retain(|f| f.ttl != Some(0));
 
-        if self.snake1.body.contains(&self.snake2.pos())
-            || self.snake2.tail().contains(&self.snake2.pos())
+        let must_spawn = self.food.is_empty();
+        let spawned = if must_spawn
+            || (self.food.len() < self.config.food.max_items
+                && self
+                    .rng
+                    .gen_bool(self.config.food.spawn_chance.clamp(0.0, 1.0)))
         {
-            self.state = BoardState::Won { is_player2: false };
+            self.spawn_food()
+        } else {
+            None
+        };
+        if let Some(food) = spawned {
+            events.push(GameEvent::FoodSpawned {
+                pos: food.pos,
+                kind: food.kind,
+            });
+        }
+        let filled = must_spawn && spawned.is_none();
+
+        let alive: Vec<_> = self.living_snakes().map(|s| s.player).collect();
+        self.state = match alive[..] {
+            [player] if self.snakes.len() > 1 => BoardState::Won { player },
+            [] if dead.len() > 1 => BoardState::Draw { players: dead },
+            [] => BoardState::AllLost,
+            _ if filled => self.filled_state(),
+            _ => BoardState::Running,
+        };
+        if self.state != BoardState::Running {
+            events.push(GameEvent::RoundEnded { state: self.state });
+        }
+        self.record_history(dead);
+
+        events
+    }
+
+    fn record_history(&mut self, dead: PlayerSet) {
+        let interval = self.update_interval();
+        let Some(history) = &mut self.history else {
             return;
+        };
+
+        history.snapshots.push_back(Snapshot {
+            snakes: self
+                .snakes
+                .iter()
+                .filter(|s| s.alive || dead.contains(s.player))
+                .cloned()
+                .collect(),
+            food: self.food.clone(),
+            crashes: self.crashes.clone(),
+            interval,
+        });
+        while history.snapshots.len() > 1
+            && history
+                .snapshots
+                .iter()
+                .map(|s| s.interval)
+                .sum::<Duration>()
+                > history.duration
+        {
+            history.snapshots.pop_front();
         }
     }
 
-    fn random_food_pos(&self) -> Dims {
-        (0..8)
-            .map(|x| (0..8).map(|y| (x, y)).collect::<Vec<_>>())
-            .flatten()
-            .filter(|pos| !self.snake1.body.contains(pos) && !self.snake2.body.contains(pos))
-            .collect::<Vec<_>>()
-            .choose(&mut thread_rng())
-            .unwrap()
-            .clone()
+    /// Why the snake dies in this update, if it does
+    fn death_cause(
+        &self,
+        snake: &Snake,
+        heads: &[Option<Dims>],
+        grows: &[bool],
+    ) -> Option<DeathCause> {
+        let Some(head) = heads[snake.player] else {
+            return Some(DeathCause::Wall);
+        };
+        let others = || {
+            self.living_snakes()
+                .filter(move |other| other.player != snake.player)
+        };
+        let covers_head = |other: &Snake| {
+            other
+                .occupied_after_move(heads[other.player].is_some(), grows[other.player])
+                .contains(&head)
+        };
+
+        if let Some(other) = others().find(|other| {
+            heads[other.player] == Some(head)
+                || (heads[other.player] == Some(snake.head()) && other.head() == head)
+        }) {
+            Some(DeathCause::HeadOn {
+                other: other.player,
+            })
+        } else if covers_head(snake) {
+            Some(DeathCause::OwnBody)
+        } else {
+            others()
+                .find(|other| covers_head(other))
+                .map(|other| DeathCause::Body {
+                    owner: other.player,
+                })
+        }
+    }
+
+    /// Result of a full board, decided by length of the living snakes
+    fn filled_state(&self) -> BoardState {
+        let longest = self.living_snakes().map(|s| s.body.len()).max();
+        BoardState::Filled {
+            players: self
+                .living_snakes()
+                .filter(|s| Some(s.body.len()) == longest)
+                .map(|s| s.player)
+                .collect(),
+        }
+    }
+
+    /// Cell next to `pos` in direction `dir`, `None` if it's a wall or
+    /// behind the edge
+    pub fn next_pos(&self, pos: Dims, dir: Dir) -> Option<Dims> {
+        let map = &self.config.map;
+        let mut next = dir.step(pos);
+        if !map.contains(next) {
+            match self.config.edge_mode {
+                EdgeMode::Wrap => {
+                    next.0 = next.0.rem_euclid(map.size.0);
+                    next.1 = next.1.rem_euclid(map.size.1);
+                }
+                EdgeMode::Wall => return None,
+            }
+        }
+
+        (!map.is_wall(next)).then_some(next)
+    }
+
+    fn living_snakes(&self) -> impl Iterator<Item = &Snake> {
+        self.snakes.iter().filter(|s| s.alive)
     }
 
-    fn move_food(&mut self) {
-        self.food = self.random_food_pos();
+    fn random_food_pos(&mut self) -> Option<Dims> {
+        let free = (0..self.config.map.size.0)
+            .flat_map(|x| (0..self.config.map.size.1).map(move |y| (x, y)))
+            .filter(|&pos| !self.config.map.is_wall(pos))
+            .filter(|pos| !self.snakes.iter().any(|s| s.alive && s.body.contains(pos)))
+            .filter(|pos| !self.food.iter().any(|f| f.pos == *pos))
+            .collect::<Vec<_>>();
+        free.choose(&mut self.rng).copied()
+    }
+
+    /// Places a new food item on a free cell, `None` if there is no free cell
+    fn spawn_food(&mut self) -> Option<Food> {
+        let pos = self.random_food_pos()?;
+        let weights = &self.config.food.weights;
+        let kind = match WeightedIndex::new(weights.iter().map(|w| w.1)) {
+            Ok(index) => weights[index.sample(&mut self.rng)].0,
+            Err(_) => FoodKind::Normal,
+        };
+        let food = Food {
+            pos,
+            kind,
+            ttl: (kind == FoodKind::Temporary).then_some(self.config.food.temporary_ticks),
+        };
+        self.food.push(food);
+        Some(food)
+    }
+
+    pub fn food(&self) -> &[Food] {
+        &self.food
+    }
+
+    /// All snakes including the dead ones, indexed by player
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    pub fn snake(&self, player: PlayerId) -> &Snake {
+        &self.snakes[player]
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.config.map
+    }
+
+    pub fn size(&self) -> Dims {
+        self.config.map.size
     }
 
     pub fn cell_size(&self) -> Dims {
@@ -111,30 +1031,324 @@ impl Board {
 
     pub fn render_size(&self) -> Dims {
         (
-            8 * self.cell_size().0 + 7 * self.border_size().0 + 2,
-            8 * self.cell_size().1 + 7 * self.border_size().1 + 2,
+            self.config.map.size.0 * self.cell_size().0
+                + (self.config.map.size.0 - 1) * self.border_size().0
+                + 2,
+            self.config.map.size.1 * self.cell_size().1
+                + (self.config.map.size.1 - 1) * self.border_size().1
+                + 2,
         )
     }
 
-    pub fn get_update_intervar() -> Duration {
-        Duration::from_millis(300)
+    /// How long to wait before the next update, shrinks as the round goes on
+    pub fn update_interval(&self) -> Duration {
+        let longest = self.living_snakes().map(|s| s.body.len()).max();
+        self.config
+            .speed
+            .interval(longest.unwrap_or(1) - 1, self.ticks)
+    }
+
+    /// Number of updates since the round started
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Seed of the current round, same seed and inputs give the same round
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn edge_mode(&self) -> EdgeMode {
+        self.config.edge_mode
+    }
+
+    pub fn config(&self) -> &BoardConfig {
+        &self.config
+    }
+
+    /// Board saved with [`Board::save`], the round goes on exactly where it
+    /// was saved
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Board drawn as a grid of characters, for rule tests and bug reports
+    ///
+    /// - `1` to `8` head of the player's snake
+    /// - `a` to `h` body of the snake of player 1 to 8
+    /// - `*` normal food, `$` golden, `!` poison, `%` temporary
+    /// - `#` wall, `.` empty cell
+    ///
+    /// Spaces around rows are ignored, so diagrams can be indented. Every
+    /// snake is alive and moves away from the segment behind its head, a
+    /// snake that's only a head faces the way it would on spawn. When a body
+    /// can be followed in more than one way, any of them is taken.
+    ///
+    /// The board uses the default config with seed 0, see
+    /// [`Board::from_ascii_with`] for others.
+    pub fn from_ascii(text: &str) -> Result<Self, AsciiError> {
+        Self::from_ascii_with(
+            text,
+            BoardConfig {
+                seed: Some(0),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// [`Board::from_ascii`] with the rest of the config, the map and the
+    /// number of players come from the diagram
+    pub fn from_ascii_with(text: &str, config: BoardConfig) -> Result<Self, AsciiError> {
+        let mut bodies = vec![Vec::new(); MAX_PLAYERS];
+        let mut food = Vec::new();
+        // Heads stay as spawns of the map, so the next round starts there
+        let mut map_text = String::new();
+        let mut y = 0;
+        for row in text.lines().map(str::trim) {
+            if !row.is_empty() && !row.starts_with(';') {
+                for (x, ch) in row.chars().enumerate() {
+                    let pos = (x as i32, y);
+                    if let Some(kind) = FoodKind::from_symbol(ch) {
+                        food.push(Food {
+                            pos,
+                            kind,
+                            ttl: (kind == FoodKind::Temporary)
+                                .then_some(config.food.temporary_ticks),
+                        });
+                        map_text.push('.');
+                    } else if let 'a'..='h' = ch {
+                        bodies[ch as usize - 'a' as usize].push(pos);
+                        map_text.push('.');
+                    } else {
+                        map_text.push(ch);
+                    }
+                }
+                y += 1;
+            }
+            map_text.push('\n');
+        }
+
+        let map = Map::parse(&map_text)?;
+        if map.spawns.is_empty() {
+            return Err(AsciiError::NoSnakes);
+        }
+        if let Some(player) = (map.spawns.len()..MAX_PLAYERS).find(|&p| !bodies[p].is_empty()) {
+            return Err(AsciiError::BrokenBody { player });
+        }
+
+        let mut board = Self::with_config(BoardConfig {
+            players: map.spawns.len(),
+            map,
+            ..config
+        });
+        // Food placed on creating the board is replaced, so is what it took
+        // from the generator
+        board.rng = ChaCha8Rng::seed_from_u64(board.seed);
+        board.food = food;
+        board.state = BoardState::Running;
+        for (player, segments) in bodies.iter().enumerate().take(board.players()) {
+            let head = board.snakes[player].head();
+            let mut body = vec![head];
+            if !board.follow_body(&mut body, segments) {
+                return Err(AsciiError::BrokenBody { player });
+            }
+            let dir = body.get(1).map(|&neck| {
+                [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+                    .into_iter()
+                    .find(|&dir| board.next_pos(neck, dir) == Some(head))
+                    .expect("Segments follow each other")
+            });
+            let snake = &mut board.snakes[player];
+            snake.dir = dir.unwrap_or(snake.dir);
+            snake.body = body;
+        }
+
+        Ok(board)
+    }
+
+    /// Adds cells of `segments` to `body` one after another until all of
+    /// them are used, `false` if they can't be
+    fn follow_body(&self, body: &mut Vec<Dims>, segments: &[Dims]) -> bool {
+        if body.len() == segments.len() + 1 {
+            return true;
+        }
+
+        let last = body[body.len() - 1];
+        for dir in [Dir::Up, Dir::Down, Dir::Left, Dir::Right] {
+            let Some(next) = self.next_pos(last, dir) else {
+                continue;
+            };
+            if segments.contains(&next) && !body.contains(&next) {
+                body.push(next);
+                if self.follow_body(body, segments) {
+                    return true;
+                }
+                body.pop();
+            }
+        }
+        false
+    }
+
+    /// Board as a diagram [`Board::from_ascii`] takes, dead snakes are left
+    /// out
+    pub fn to_ascii(&self) -> String {
+        let (width, height) = self.size();
+        let mut rows = vec![vec!['.'; width as usize]; height as usize];
+        let mut set = |(x, y): Dims, ch| rows[y as usize][x as usize] = ch;
+
+        for &wall in self.config.map.walls.iter() {
+            set(wall, '#');
+        }
+        for food in self.food.iter() {
+            set(food.pos, food.kind.symbol());
+        }
+        for snake in self.living_snakes() {
+            for (i, &block) in snake.body.iter().enumerate() {
+                let first = if i == 0 { b'1' } else { b'a' };
+                set(block, (first + snake.player as u8) as char);
+            }
+        }
+
+        rows.into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect()
     }
 
     pub fn reset(&mut self) {
-        *self = Self::new();
+        let history = self.history.take();
+        *self = Self::with_config(self.config.clone());
+        if let Some(history) = history {
+            self.keep_history(history.duration);
+        }
+    }
+
+    /// Starts keeping the updates of the last `duration` for
+    /// [`Board::instant_replay`]
+    pub fn keep_history(&mut self, duration: Duration) {
+        self.history = Some(History {
+            duration,
+            snapshots: VecDeque::new(),
+        });
+    }
+
+    /// Updates that led to the end of the round, oldest first, empty when no
+    /// history is kept
+    ///
+    /// The last one shows the snakes that died where they died, with the
+    /// cells they crashed into marked.
+    pub fn instant_replay(&self) -> Vec<ReplayFrame<'_>> {
+        self.history
+            .iter()
+            .flat_map(|history| history.snapshots.iter())
+            .map(|snapshot| ReplayFrame {
+                board: self,
+                snapshot,
+            })
+            .collect()
+    }
+
+    /// Cells snakes crashed into on the last update
+    pub fn crashes(&self) -> &[Dims] {
+        &self.crashes
     }
 
     pub fn get_state(&self) -> BoardState {
         self.state
     }
 
-    pub fn get_scores(&self) -> (usize, usize) {
-        (self.snake1.body.len(), self.snake2.body.len())
+    pub fn players(&self) -> usize {
+        self.snakes.len()
+    }
+
+    pub fn player_color(&self, player: PlayerId) -> Color {
+        self.snakes[player].color()
+    }
+
+    /// Length of each player's snake, indexed by player
+    pub fn get_scores(&self) -> Vec<usize> {
+        self.snakes.iter().map(|s| s.body.len()).collect()
+    }
+
+    /// Hash of everything that decides how the round goes on, boards with
+    /// different checksums don't play out the same way
+    ///
+    /// It's the same on every platform, so two machines playing one round
+    /// can compare it to notice they got out of sync.
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv::new();
+        hash.write(&self.ticks.to_le_bytes());
+        hash.write(&self.rng.get_word_pos().to_le_bytes());
+        hash.write(&match self.state {
+            BoardState::Running => [0, 0],
+            BoardState::Won { player } => [1, player as u8],
+            BoardState::Draw { players } => [2, players.0],
+            BoardState::AllLost => [3, 0],
+            BoardState::Filled { players } => [4, players.0],
+        });
+        for snake in self.snakes.iter() {
+            hash.write(&[snake.alive as u8, snake.dir as u8]);
+            hash.write(&(snake.growth as u64).to_le_bytes());
+            hash.write(&(snake.turns.len() as u64).to_le_bytes());
+            hash.write(&snake.turns.iter().map(|&dir| dir as u8).collect::<Vec<_>>());
+            hash.write(&(snake.body.len() as u64).to_le_bytes());
+            for pos in snake.body.iter() {
+                hash.write_pos(*pos);
+            }
+        }
+        for food in self.food.iter() {
+            hash.write_pos(food.pos);
+            hash.write(&[food.kind as u8]);
+            hash.write(&food.ttl.unwrap_or(u32::MAX).to_le_bytes());
+        }
+        hash.0
     }
 }
 
+/// 64 bit FNV-1a, unlike the std hashers it never changes between platforms
+/// or Rust versions
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_pos(&mut self, pos: Dims) {
+        self.write(&pos.0.to_le_bytes());
+        self.write(&pos.1.to_le_bytes());
+    }
+}
+
+#[cfg(feature = "render")]
 impl Drawable for Board {
     fn draw(&self, pos: Dims, frame: &mut impl CanvasLike) {
+        self.draw_with(pos, frame, None);
+    }
+}
+
+#[cfg(feature = "render")]
+impl Drawable for ReplayFrame<'_> {
+    fn draw(&self, pos: Dims, frame: &mut impl CanvasLike) {
+        self.board.draw_with(pos, frame, Some(self.snapshot));
+    }
+}
+
+#[cfg(feature = "render")]
+impl Board {
+    /// Draws the board as it is or, with a snapshot, as it was then
+    fn draw_with(&self, pos: Dims, frame: &mut impl CanvasLike, snapshot: Option<&Snapshot>) {
         let size = self.render_size();
 
         UiBox::new(size.into(), ContentStyle::new()).draw(pos, frame);
@@ -148,64 +1362,159 @@ impl Drawable for Board {
                 .with_size(self.cell_size())
         };
 
-        for x in 0..7 {
+        for x in 0..self.config.map.size.0 - 1 {
             Frame::new(inner.clone()) // cuz clip
                 .l((self.cell_size().0 + self.border_size().0) * (x + 1))
                 .r(self.border_size().0)
                 .fill(Cell::styled(' ', ContentStyle::new().on_white()));
         }
 
-        for y in 0..7 {
+        for y in 0..self.config.map.size.1 - 1 {
             Frame::new(inner.clone()) // cuz clip
                 .t((self.cell_size().1 + self.border_size().1) * (y + 1))
                 .b(self.border_size().1)
                 .fill(Cell::styled(' ', ContentStyle::new().on_white()));
         }
 
-        match self.state {
-            BoardState::Running => {
-                for snake in [&self.snake1, &self.snake2] {
-                    for block in snake.body.iter() {
-                        make_cell_frame(*block)
-                            .fill(Cell::styled(' ', ContentStyle::new().on(snake.color)));
-                    }
-                }
+        for (y, row) in self.looks(snapshot).into_iter().enumerate() {
+            for (x, (ch, style)) in row.into_iter().enumerate() {
+                make_cell_frame((x as i32, y as i32)).fill(Cell::styled(ch, style));
+            }
+        }
+    }
+}
+
+impl Board {
+    /// What every cell shows, as rows of characters with their style
+    ///
+    /// Every way of drawing the board paints cells from this, so the board
+    /// looks the same in the game and in a remote terminal.
+    pub fn cell_looks(&self) -> Vec<Vec<(char, ContentStyle)>> {
+        self.looks(None)
+    }
+
+    /// Looks of the cells as they are or, with a snapshot, as they were then
+    fn looks(&self, snapshot: Option<&Snapshot>) -> Vec<Vec<(char, ContentStyle)>> {
+        let (width, height) = self.config.map.size;
+        let mut looks = vec![vec![(' ', ContentStyle::new()); width as usize]; height as usize];
+        let mut paint = |pos: Dims, ch: char, style: ContentStyle| {
+            looks[pos.1 as usize][pos.0 as usize] = (ch, style);
+        };
 
-                make_cell_frame(self.food).fill(Cell::styled(' ', ContentStyle::new().on_yellow()));
+        match (snapshot, self.state) {
+            (Some(snapshot), _) => self.paint_round(
+                &mut paint,
+                snapshot.snakes.iter(),
+                &snapshot.food,
+                &snapshot.crashes,
+            ),
+            (None, BoardState::Running) => {
+                self.paint_round(&mut paint, self.living_snakes(), &self.food, &[])
             }
-            BoardState::Won { is_player2 } => {
-                for x in 0..8 {
-                    for y in 0..8 {
-                        make_cell_frame((x, y)).fill(Cell::styled(
+            (None, BoardState::Won { player }) => {
+                for x in 0..width {
+                    for y in 0..height {
+                        paint(
+                            (x, y),
                             ' ',
-                            ContentStyle::new().on(value_if_else(
-                                is_player2,
-                                || self.snake2.color,
-                                || self.snake1.color,
-                            )),
-                        ));
+                            ContentStyle::new().on(self.snakes[player].color()),
+                        );
                     }
                 }
             }
-            BoardState::BothLost => {
-                for x in 0..8 {
-                    for y in 0..8 {
-                        make_cell_frame((x, y))
-                            .fill(Cell::styled(' ', ContentStyle::new().on(Color::Red)));
+            (None, BoardState::Draw { players } | BoardState::Filled { players }) => {
+                // Diagonal stripes in the colors of everyone who shares the result
+                let colors: Vec<_> = players.iter().map(|p| self.snakes[p].color()).collect();
+                for x in 0..width {
+                    for y in 0..height {
+                        let color = colors[(x + y) as usize % colors.len()];
+                        paint((x, y), ' ', ContentStyle::new().on(color));
                     }
                 }
-                for x in 1..7 {
-                    make_cell_frame((x, x))
-                        .fill(Cell::styled(' ', ContentStyle::new().on(Color::Black)));
-                    make_cell_frame((x, 7 - x))
-                        .fill(Cell::styled(' ', ContentStyle::new().on(Color::Black)));
+            }
+            (None, BoardState::AllLost) => {
+                for x in 0..width {
+                    for y in 0..height {
+                        paint((x, y), ' ', ContentStyle::new().on(Color::Red));
+                    }
+                }
+                // Cross from corner to corner, scaled for non-square boards
+                for x in 1..width - 1 {
+                    let y = (x * (height - 1) + (width - 1) / 2) / (width - 1);
+                    paint((x, y), ' ', ContentStyle::new().on(Color::Black));
+                    paint(
+                        (x, height - 1 - y),
+                        ' ',
+                        ContentStyle::new().on(Color::Black),
+                    );
                 }
             }
         }
+
+        looks
+    }
+
+    /// Walls, snakes and food of a running round, `crashes` are marked on top
+    fn paint_round<'a>(
+        &self,
+        paint: &mut impl FnMut(Dims, char, ContentStyle),
+        snakes: impl Iterator<Item = &'a Snake>,
+        food: &[Food],
+        crashes: &[Dims],
+    ) {
+        for wall in self.config.map.walls.iter() {
+            paint(*wall, '▓', ContentStyle::new().grey().on_black());
+        }
+
+        for snake in snakes {
+            for block in snake.body.iter() {
+                paint(*block, ' ', ContentStyle::new().on(snake.color()));
+            }
+        }
+
+        for food in food.iter() {
+            paint(food.pos, ' ', ContentStyle::new().on(food.kind.color()));
+        }
+
+        for crash in crashes.iter() {
+            paint(*crash, '╳', ContentStyle::new().white().on_red());
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+fn starting_food(map: &Map) -> Vec<Food> {
+    map.food
+        .iter()
+        .map(|&pos| Food {
+            pos,
+            kind: FoodKind::Normal,
+            ttl: None,
+        })
+        .collect()
+}
+
+/// Direction a snake starting at `pos` should face, the one with the longest
+/// run of free cells in front of it
+fn spawn_dir(map: &Map, pos: Dims) -> Dir {
+    let run = |dir: Dir| {
+        let mut cell = dir.step(pos);
+        let mut len = 0;
+        while map.contains(cell) && !map.is_wall(cell) {
+            cell = dir.step(cell);
+            len += 1;
+        }
+        len
+    };
+
+    // `max_by_key` picks the last of equal ones, so horizontal wins ties
+    [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+        .into_iter()
+        .max_by_key(|&dir| run(dir))
+        .unwrap()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Dir {
     Up,
     Down,
@@ -213,68 +1522,397 @@ pub enum Dir {
     Right,
 }
 
+impl Dir {
+    /// Position one cell away in this direction, may be outside the board
+    pub fn step(self, pos: Dims) -> Dims {
+        match self {
+            Dir::Up => (pos.0, pos.1 - 1),
+            Dir::Down => (pos.0, pos.1 + 1),
+            Dir::Left => (pos.0 - 1, pos.1),
+            Dir::Right => (pos.0 + 1, pos.1),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Dir::Up => Dir::Down,
+            Dir::Down => Dir::Up,
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Snake {
+    player: PlayerId,
     body: Vec<Dims>,
-    color: Color,
     dir: Dir,
-    last_end: Dims,
+    turns: VecDeque<Dir>,
+    /// Segments left to grow, the tail stays in place until it's 0
+    growth: usize,
+    alive: bool,
 }
 
 impl Snake {
-    fn is_on_valid_pos(pos: Dims) -> bool {
-        pos.0 >= 0 && pos.1 >= 0 && pos.0 < 8 && pos.1 < 8
-    }
-
-    fn new(pos: Dims, color: Color) -> Self {
-        if !Self::is_on_valid_pos(pos) {
-            panic!("Invalid position");
-        }
+    fn new(player: PlayerId, pos: Dims, dir: Dir) -> Self {
         Self {
+            player,
             body: vec![pos],
-            color,
-            dir: Dir::Right,
-            last_end: pos,
+            dir,
+            turns: VecDeque::with_capacity(TURN_QUEUE_LEN),
+            growth: 0,
+            alive: true,
         }
     }
 
-    fn pos(&self) -> Dims {
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Cells of the snake from the head to the tail
+    pub fn body(&self) -> &[Dims] {
+        &self.body
+    }
+
+    pub fn head(&self) -> Dims {
         self.body[0]
     }
 
-    fn tail(&self) -> &[Dims] {
-        &self.body[1..]
+    /// Direction the snake moved in on the last update, queued turns aren't
+    /// taken yet
+    pub fn dir(&self) -> Dir {
+        self.dir
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Whether the tail stays in place on the next move
+    pub fn is_growing(&self) -> bool {
+        self.growth > 0
     }
 
-    fn go(&mut self, dir: Option<Dir>) {
-        if let Some(dir) = dir {
-            self.dir = dir;
+    pub fn color(&self) -> Color {
+        PLAYER_COLORS[self.player]
+    }
+
+    /// Takes the next queued turn, skipping ones that would reverse the snake
+    fn turn(&mut self) {
+        while let Some(dir) = self.turns.pop_front() {
+            if dir != self.dir.opposite() || self.body.len() == 1 {
+                self.dir = dir;
+                break;
+            }
         }
+    }
 
-        let mut new_head = self.body[0];
-        match self.dir {
-            Dir::Up => new_head.1 -= 1,
-            Dir::Down => new_head.1 += 1,
-            Dir::Left => new_head.0 -= 1,
-            Dir::Right => new_head.0 += 1,
+    /// Cells still covered after the move, not counting the new head
+    fn occupied_after_move(&self, moves: bool, grows: bool) -> &[Dims] {
+        if moves && !grows {
+            &self.body[..self.body.len() - 1]
+        } else {
+            &self.body
         }
+    }
 
-        if !Self::is_on_valid_pos(new_head) {
-            if TELEPORT_ON_EDGE {
-                if new_head.0 < 0 {
-                    new_head.0 = 7;
-                } else if new_head.0 > 7 {
-                    new_head.0 = 0;
-                } else if new_head.1 < 0 {
-                    new_head.1 = 7;
-                } else if new_head.1 > 7 {
-                    new_head.1 = 0;
-                }
-            } else {
-                panic!("Invalid position");
+    /// Moves the head to `head`, the tail follows unless the snake is growing
+    fn advance(&mut self, head: Dims) {
+        self.body.insert(0, head);
+        if self.growth > 0 {
+            self.growth -= 1;
+        } else {
+            self.body.pop();
+        }
+    }
+
+    /// Cuts segments off the tail, the head always stays
+    fn shrink(&mut self, segments: usize) {
+        let len = self.body.len().saturating_sub(segments).max(1);
+        self.body.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn walled(text: &str) -> Board {
+        Board::from_ascii_with(
+            text,
+            BoardConfig {
+                edge_mode: EdgeMode::Wall,
+                seed: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// Diagram the way [`Board::to_ascii`] writes it
+    fn diagram(text: &str) -> String {
+        text.lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .map(|row| format!("{}\n", row))
+            .collect()
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let text = "
+            #....
+            1aa*.
+            ..$!2
+        ";
+        let board = Board::from_ascii(text).unwrap();
+        assert_eq!(board.to_ascii(), diagram(text));
+        assert_eq!(board.players(), 2);
+        assert_eq!(board.snake(0).body(), &[(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(board.snake(0).dir(), Dir::Left);
+        assert!(board.map().is_wall((0, 0)));
+    }
+
+    #[test]
+    fn snakes_move_forward() {
+        let mut board = walled(
+            "
+            a1...
+            .....
+            *...2
+        ",
+        );
+        board.update(&[None, None]);
+        assert_eq!(
+            board.to_ascii(),
+            diagram(
+                "
+                .a1..
+                .....
+                *..2.
+            "
+            )
+        );
+    }
+
+    #[test]
+    fn eating_grows_right_away() {
+        let mut board = walled(
+            "
+            a1*.
+            ....
+            2..*
+        ",
+        );
+        let events = board.update(&[None, Some(Dir::Up)]);
+        assert_eq!(
+            board.to_ascii(),
+            diagram(
+                "
+                aa1.
+                2...
+                ...*
+            "
+            )
+        );
+        assert!(events.contains(&GameEvent::FoodEaten {
+            player: 0,
+            pos: (2, 0),
+            kind: FoodKind::Normal
+        }));
+    }
+
+    #[test]
+    fn poison_shrinks() {
+        let mut board = walled(
+            "
+            aaa1!
+            .....
+            ....*
+        ",
+        );
+        board.update(&[None]);
+        assert_eq!(
+            board.to_ascii(),
+            diagram(
+                "
+                ...a1
+                .....
+                ....*
+            "
+            )
+        );
+    }
+
+    #[test]
+    fn cell_of_a_moving_tail_is_safe() {
+        let mut board = walled(
+            "
+            1bb2
+            ....
+            ...*
+        ",
+        );
+        board.update(&[Some(Dir::Right), Some(Dir::Down)]);
+        assert_eq!(board.get_state(), BoardState::Running);
+        assert_eq!(
+            board.to_ascii(),
+            diagram(
+                "
+                .1bb
+                ...2
+                ...*
+            "
+            )
+        );
+    }
+
+    #[test]
+    fn heads_meeting_kill_both() {
+        let mut board = walled(
+            "
+            1.2
+            ...
+            ..*
+        ",
+        );
+        board.update(&[Some(Dir::Right), Some(Dir::Left)]);
+        assert_eq!(
+            board.get_state(),
+            BoardState::Draw {
+                players: [0, 1].into_iter().collect()
             }
+        );
+        assert_eq!(board.crashes(), &[(1, 0)]);
+    }
+
+    #[test]
+    fn heads_swapping_kill_both() {
+        let mut board = walled(
+            "
+            a12b
+            ....
+            ...*
+        ",
+        );
+        let events = board.update(&[None, None]);
+        assert!(events.contains(&GameEvent::Died {
+            player: 0,
+            cause: DeathCause::HeadOn { other: 1 }
+        }));
+        assert!(matches!(board.get_state(), BoardState::Draw { .. }));
+    }
+
+    #[test]
+    fn own_body_kills() {
+        let mut board = walled(
+            "
+            aaa.
+            a1..
+            ...*
+        ",
+        );
+        let events = board.update(&[Some(Dir::Up)]);
+        assert!(events.contains(&GameEvent::Died {
+            player: 0,
+            cause: DeathCause::OwnBody
+        }));
+        assert_eq!(board.get_state(), BoardState::AllLost);
+    }
+
+    #[test]
+    fn edges_wrap_or_kill() {
+        let text = "
+            a1
+            ..
+            *.
+        ";
+        let mut board = Board::from_ascii(text).unwrap();
+        board.update(&[None]);
+        assert_eq!(board.to_ascii(), diagram("1a\n..\n*."));
+
+        let mut board = walled(text);
+        let events = board.update(&[None]);
+        assert!(events.contains(&GameEvent::Died {
+            player: 0,
+            cause: DeathCause::Wall
+        }));
+    }
+
+    #[test]
+    fn saved_boards_play_on_the_same() {
+        let mut board = Board::from_ascii_with(
+            "
+            1a.....
+            .....%.
+            ..#.#..
+            .$...!.
+            ....b2.
+        ",
+            BoardConfig {
+                seed: Some(3),
+                food: FoodConfig::chaos(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        board.update(&[Some(Dir::Down), None]);
+
+        let path = std::env::temp_dir().join(format!("double-snake-{}.json", std::process::id()));
+        board.save(&path).unwrap();
+        let mut loaded = Board::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.checksum(), board.checksum());
+
+        for dirs in [
+            [None, Some(Dir::Up)],
+            [Some(Dir::Right), None],
+            [None, None],
+        ] {
+            board.update(&dirs);
+            loaded.update(&dirs);
         }
+        assert_eq!(loaded.checksum(), board.checksum());
+        assert_eq!(loaded.to_ascii(), board.to_ascii());
+    }
+
+    #[test]
+    fn broken_saves_are_rejected() {
+        let board = Board::from_ascii("1..\n..2").unwrap();
+        let rejects = |key: &str, value: serde_json::Value| {
+            let mut json = serde_json::to_value(&board).unwrap();
+            json[key] = value;
+            serde_json::from_value::<Board>(json).is_err()
+        };
+
+        assert!(rejects("state", json!({"result": "filled", "players": []})));
+        assert!(rejects("state", json!({"result": "won", "player": 5})));
+        assert!(rejects("crashes", json!([[3, 0]])));
+        assert!(rejects("snakes", json!([])));
+        let mut config = serde_json::to_value(board.config()).unwrap();
+        config["players"] = json!(9);
+        assert!(rejects("config", config));
+    }
 
-        self.body.insert(0, new_head);
-        self.last_end = self.body.pop().unwrap();
+    #[test]
+    fn broken_diagrams_are_rejected() {
+        let error = |text| Board::from_ascii(text).err().unwrap();
+        assert!(matches!(
+            error("1.a.\n...."),
+            AsciiError::BrokenBody { player: 0 }
+        ));
+        assert!(matches!(
+            error("1..\nb.."),
+            AsciiError::BrokenBody { player: 1 }
+        ));
+        assert!(matches!(error("...\n..."), AsciiError::NoSnakes));
+        assert!(matches!(error("12"), AsciiError::Map(MapError::TooSmall)));
+        assert!(matches!(
+            error("1.\n.x"),
+            AsciiError::Map(MapError::UnknownChar { ch: 'x', .. })
+        ));
     }
 }
@@ -0,0 +1,172 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    board::{Board, Dims, Dir, EdgeMode, FoodKind, PlayerId},
+    controller::SnakeController,
+};
+
+/// Bot running as a separate program, so it can be written in any language
+///
+/// Before every update the program gets the board as one line of JSON on its
+/// stdin, for example
+///
+/// ```text
+/// {"tick":3,"you":1,"width":8,"height":8,"edges":"wrap","walls":[[3,4]],
+///  "snakes":[{"player":0,"alive":true,"dir":"right","body":[[3,0],[2,0]]},
+///            {"player":1,"alive":true,"dir":"left","body":[[4,7]]}],
+///  "food":[{"pos":[5,5],"kind":"normal"}]}
+/// ```
+///
+/// Positions are `[x, y]`, bodies start at the head. The program answers
+/// with one line on its stdout, `up`, `down`, `left` or `right`. Any other
+/// line, an answer that takes longer than the timeout or a crash keeps the
+/// snake going straight.
+pub struct ExternalBot {
+    child: Child,
+    /// `None` once writing to the program failed
+    stdin: Option<ChildStdin>,
+    lines: Receiver<String>,
+    timeout: Duration,
+}
+
+#[derive(Serialize)]
+struct State<'a> {
+    tick: u32,
+    you: PlayerId,
+    width: i32,
+    height: i32,
+    edges: EdgeMode,
+    walls: Vec<Dims>,
+    snakes: Vec<SnakeState<'a>>,
+    food: Vec<FoodState>,
+}
+
+#[derive(Serialize)]
+struct SnakeState<'a> {
+    player: PlayerId,
+    alive: bool,
+    dir: Dir,
+    body: &'a [Dims],
+}
+
+#[derive(Serialize)]
+struct FoodState {
+    pos: Dims,
+    kind: FoodKind,
+}
+
+impl ExternalBot {
+    /// Starts `command`, split on whitespace into the program and its
+    /// arguments
+    pub fn spawn(command: &str, timeout: Duration) -> io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Bot command is empty"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // The terminal belongs to the game
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // Lines are read on their own thread so waiting for them can time out
+        let stdout = child.stdout.take().expect("Stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            stdin: child.stdin.take(),
+            child,
+            lines,
+            timeout,
+        })
+    }
+
+    fn send_state(&mut self, board: &Board, player: PlayerId) -> io::Result<()> {
+        let Some(stdin) = &mut self.stdin else {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        };
+
+        let (width, height) = board.size();
+        let state = State {
+            tick: board.ticks(),
+            you: player,
+            width,
+            height,
+            edges: board.edge_mode(),
+            walls: board.map().walls.iter().copied().collect(),
+            snakes: board
+                .snakes()
+                .iter()
+                .map(|s| SnakeState {
+                    player: s.player(),
+                    alive: s.is_alive(),
+                    dir: s.dir(),
+                    body: s.body(),
+                })
+                .collect(),
+            food: board
+                .food()
+                .iter()
+                .map(|f| FoodState {
+                    pos: f.pos,
+                    kind: f.kind,
+                })
+                .collect(),
+        };
+
+        serde_json::to_writer(&mut *stdin, &state)?;
+        stdin.write_all(b"\n")?;
+        stdin.flush()
+    }
+}
+
+impl SnakeController for ExternalBot {
+    fn next_dir(&mut self, board: &Board, player: PlayerId) -> Option<Dir> {
+        if !board.snake(player).is_alive() {
+            return None;
+        }
+
+        // Answers that came too late were meant for an older board
+        while self.lines.try_recv().is_ok() {}
+
+        if self.send_state(board, player).is_err() {
+            self.stdin = None;
+            return None;
+        }
+
+        match self.lines.recv_timeout(self.timeout).ok()?.trim() {
+            "up" => Some(Dir::Up),
+            "down" => Some(Dir::Down),
+            "left" => Some(Dir::Left),
+            "right" => Some(Dir::Right),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        // Closing stdin lets well behaved bots quit on their own
+        self.stdin = None;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
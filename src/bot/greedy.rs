@@ -0,0 +1,169 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::Difficulty;
+use crate::{
+    board::{Board, Dims, Dir, FoodKind, PlayerId},
+    controller::SnakeController,
+};
+
+const DIRS: [Dir; 4] = [Dir::Up, Dir::Down, Dir::Left, Dir::Right];
+
+/// Bot heading for the closest food by the shortest path, wrapping around
+/// the edges when the board does
+///
+/// Easy bots only avoid running into things, the others also don't enter
+/// areas too small to fit their snake and keep away from cells other heads
+/// can reach on the same update.
+pub struct GreedyBot {
+    difficulty: Difficulty,
+    rng: ChaCha8Rng,
+    /// Decisions waiting for the reaction delay to pass
+    planned: VecDeque<Option<Dir>>,
+}
+
+/// What can be reached from a cell
+struct Reach {
+    /// Number of free cells
+    area: usize,
+    /// Steps to the closest food worth eating
+    food: Option<usize>,
+}
+
+impl GreedyBot {
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        Self {
+            difficulty,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            planned: VecDeque::new(),
+        }
+    }
+
+    fn decide(&mut self, board: &Board, player: PlayerId) -> Option<Dir> {
+        let snake = board.snake(player);
+        if !snake.is_alive() {
+            return None;
+        }
+
+        let blocked = blocked_cells(board);
+        let moves: Vec<_> = DIRS
+            .into_iter()
+            .filter(|&dir| dir != snake.dir().opposite() || snake.body().len() == 1)
+            .filter_map(|dir| Some((dir, board.next_pos(snake.head(), dir)?)))
+            .filter(|(_, pos)| !blocked.contains(pos))
+            .collect();
+        if self.rng.gen_bool(self.difficulty.mistake_chance()) {
+            return moves.choose(&mut self.rng).map(|&(dir, _)| dir);
+        }
+
+        let mut options: Vec<_> = moves
+            .iter()
+            .map(|&(dir, pos)| (dir, pos, explore(board, pos, &blocked)))
+            .collect();
+
+        if self.difficulty != Difficulty::Easy {
+            let contested = contested_cells(board, player);
+            if options.iter().any(|o| !contested.contains(&o.1)) {
+                options.retain(|o| !contested.contains(&o.1));
+            }
+
+            let len = snake.body().len();
+            let largest = options.iter().map(|o| o.2.area).max().unwrap_or(0);
+            // When everything is too small, the largest area buys the most time
+            options.retain(|o| o.2.area >= len.min(largest));
+        }
+
+        options
+            .iter()
+            .min_by_key(|(dir, _, reach)| {
+                (
+                    reach.food.unwrap_or(usize::MAX),
+                    *dir != snake.dir(), // Keep going straight on ties
+                )
+            })
+            .map(|&(dir, _, _)| dir)
+    }
+}
+
+impl SnakeController for GreedyBot {
+    fn next_dir(&mut self, board: &Board, player: PlayerId) -> Option<Dir> {
+        let decision = self.decide(board, player);
+        self.planned.push_back(decision);
+        if self.planned.len() > self.difficulty.reaction_delay() {
+            self.planned.pop_front().flatten()
+        } else {
+            None
+        }
+    }
+
+    fn reset(&mut self) {
+        self.planned.clear();
+    }
+}
+
+/// Cells of living snakes that are still taken after the next update, tails
+/// of snakes that don't grow move away
+fn blocked_cells(board: &Board) -> BTreeSet<Dims> {
+    board
+        .snakes()
+        .iter()
+        .filter(|s| s.is_alive())
+        .flat_map(|s| {
+            let body = s.body();
+            if s.is_growing() {
+                body
+            } else {
+                // Heads can't be passed through even on snakes of length 1
+                &body[..(body.len() - 1).max(1)]
+            }
+        })
+        .copied()
+        .collect()
+}
+
+/// Cells other snakes' heads can move to on the next update
+fn contested_cells(board: &Board, player: PlayerId) -> BTreeSet<Dims> {
+    board
+        .snakes()
+        .iter()
+        .filter(|s| s.is_alive() && s.player() != player)
+        .flat_map(|s| {
+            DIRS.into_iter()
+                .filter_map(|dir| board.next_pos(s.head(), dir))
+        })
+        .collect()
+}
+
+/// Breadth first search over free cells starting at `start`
+fn explore(board: &Board, start: Dims, blocked: &BTreeSet<Dims>) -> Reach {
+    let targets: BTreeSet<_> = board
+        .food()
+        .iter()
+        .filter(|f| f.kind != FoodKind::Poison)
+        .map(|f| f.pos)
+        .collect();
+
+    let mut seen = BTreeSet::from([start]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    let mut food = None;
+    while let Some((pos, dist)) = queue.pop_front() {
+        if food.is_none() && targets.contains(&pos) {
+            food = Some(dist);
+        }
+        for dir in DIRS {
+            match board.next_pos(pos, dir) {
+                Some(next) if !blocked.contains(&next) && seen.insert(next) => {
+                    queue.push_back((next, dist + 1))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Reach {
+        area: seen.len(),
+        food,
+    }
+}
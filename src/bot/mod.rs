@@ -0,0 +1,30 @@
+pub mod external;
+pub mod greedy;
+pub mod search;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Updates between the bot seeing the board and its turn being taken
+    fn reaction_delay(self) -> usize {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 0,
+        }
+    }
+
+    /// Chance of turning somewhere random instead of following the plan
+    fn mistake_chance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.1,
+            Difficulty::Normal => 0.02,
+            Difficulty::Hard => 0.,
+        }
+    }
+}
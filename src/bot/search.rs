@@ -0,0 +1,249 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{Board, BoardState, Dir, FoodKind, PlayerId},
+    controller::SnakeController,
+};
+
+const DIRS: [Dir; 4] = [Dir::Up, Dir::Down, Dir::Left, Dir::Right];
+
+/// Scores of finished rounds, far out of the range `evaluate` gives
+const WIN: i32 = 1_000_000;
+const LOSS: i32 = -WIN;
+
+/// Even with time left the search stops here, it wouldn't finish anyway
+pub const MAX_DEPTH: usize = 16;
+
+/// Bot looking a few updates ahead for itself and its closest opponent
+///
+/// Both snakes move at the same time, so the bot expects the opponent to
+/// always answer with the move that's worst for it. Positions are rated by
+/// how many cells each snake can reach first, which makes the bot cut the
+/// opponent off and herd it into dead ends. It searches one update deeper at
+/// a time until the time budget runs out and plays the deepest finished
+/// result.
+///
+/// How deep that gets depends on the machine and how busy it is, a bot made
+/// with [`SearchBot::with_depth`] plays the same moves everywhere.
+pub struct SearchBot {
+    limit: Limit,
+}
+
+enum Limit {
+    Time(Duration),
+    Depth(usize),
+}
+
+impl SearchBot {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            limit: Limit::Time(budget),
+        }
+    }
+
+    /// Bot that always looks `depth` updates ahead, however long it takes,
+    /// from 1 to [`MAX_DEPTH`]
+    pub fn with_depth(depth: usize) -> Self {
+        Self {
+            limit: Limit::Depth(depth.clamp(1, MAX_DEPTH)),
+        }
+    }
+}
+
+impl SnakeController for SearchBot {
+    fn next_dir(&mut self, board: &Board, player: PlayerId) -> Option<Dir> {
+        if board.get_state() != BoardState::Running || !board.snake(player).is_alive() {
+            return None;
+        }
+
+        let opponent = closest_opponent(board, player);
+        let budget = match self.limit {
+            Limit::Time(budget) => budget,
+            Limit::Depth(depth) => {
+                let search = Search {
+                    player,
+                    opponent,
+                    deadline: None,
+                };
+                return search.best_move(board, depth);
+            }
+        };
+
+        // Drawing and the other players need the rest of the update
+        let deadline = Instant::now() + budget.min(board.update_interval() / 2);
+        let mut best = None;
+        for depth in 1..=MAX_DEPTH {
+            let search = Search {
+                player,
+                opponent,
+                // The shallowest search always finishes so there's a move
+                deadline: (depth > 1).then_some(deadline),
+            };
+            match search.best_move(board, depth) {
+                Some(dir) => best = Some(dir),
+                None => break,
+            }
+        }
+
+        best
+    }
+}
+
+struct Search {
+    player: PlayerId,
+    opponent: Option<PlayerId>,
+    deadline: Option<Instant>,
+}
+
+impl Search {
+    /// `None` when the time ran out before the search finished
+    fn best_move(&self, board: &Board, depth: usize) -> Option<Dir> {
+        let mut best = None;
+        let mut best_value = i32::MIN;
+        for dir in moves(board, self.player) {
+            let value = self.worst_reply(board, dir, depth, best_value)?;
+            if value > best_value {
+                best = Some(dir);
+                best_value = value;
+            }
+        }
+
+        best
+    }
+
+    /// Value of the position for the bot, `None` when out of time
+    fn value(&self, board: &Board, depth: usize) -> Option<i32> {
+        if let Some(value) = self.outcome(board) {
+            return Some(value);
+        }
+        if depth == 0 {
+            return Some(self.evaluate(board));
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return None;
+        }
+
+        let mut best = i32::MIN;
+        for dir in moves(board, self.player) {
+            best = best.max(self.worst_reply(board, dir, depth, best)?);
+        }
+        Some(best)
+    }
+
+    /// Value after the bot moves in `dir` and the opponent answers as badly
+    /// for it as it can, stops early once it's no better than `cutoff`
+    fn worst_reply(&self, board: &Board, dir: Dir, depth: usize, cutoff: i32) -> Option<i32> {
+        let replies = match self.opponent {
+            Some(opponent) => moves(board, opponent).into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+
+        let mut worst = i32::MAX;
+        for reply in replies {
+            let mut dirs = vec![None; board.players()];
+            dirs[self.player] = Some(dir);
+            if let (Some(opponent), Some(reply)) = (self.opponent, reply) {
+                dirs[opponent] = Some(reply);
+            }
+
+            let mut next = board.clone();
+            next.update(&dirs);
+            worst = worst.min(self.value(&next, depth - 1)?);
+            if worst <= cutoff {
+                break;
+            }
+        }
+        Some(worst)
+    }
+
+    /// Value of a position that's decided already
+    fn outcome(&self, board: &Board) -> Option<i32> {
+        let alive = |player| board.snake(player).is_alive();
+        match board.get_state() {
+            BoardState::Running if !alive(self.player) => Some(LOSS),
+            BoardState::Running if self.opponent.is_some_and(|o| !alive(o)) => Some(WIN / 2),
+            BoardState::Running => None,
+            BoardState::Won { player } if player == self.player => Some(WIN),
+            BoardState::Filled { players } if players.contains(self.player) => Some(WIN / 2),
+            // Better than losing alone, still not something to aim for
+            BoardState::Draw { .. } => Some(LOSS / 2),
+            _ => Some(LOSS),
+        }
+    }
+
+    /// Rates a running round by the space and food each snake gets to first
+    /// and by the difference in length
+    fn evaluate(&self, board: &Board) -> i32 {
+        let (width, height) = board.size();
+        let index = |pos: (i32, i32)| (pos.1 * width + pos.0) as usize;
+
+        // Distance from the bot (0) and the opponent (1) to every cell
+        let mut dists = vec![[usize::MAX; 2]; (width * height) as usize];
+        for snake in board.snakes().iter().filter(|s| s.is_alive()) {
+            for &pos in snake.body() {
+                dists[index(pos)] = [0, 0];
+            }
+        }
+        let mut queue = VecDeque::new();
+        for (side, player) in [Some(self.player), self.opponent].into_iter().enumerate() {
+            if let Some(player) = player {
+                queue.push_back((board.snake(player).head(), side, 0));
+            }
+        }
+        while let Some((pos, side, dist)) = queue.pop_front() {
+            for dir in DIRS {
+                let Some(next) = board.next_pos(pos, dir) else {
+                    continue;
+                };
+                let cell = &mut dists[index(next)];
+                if cell[side] == usize::MAX && cell[1 - side] > dist {
+                    cell[side] = dist + 1;
+                    queue.push_back((next, side, dist + 1));
+                }
+            }
+        }
+
+        let owner = |pos| {
+            let [mine, theirs] = dists[index(pos)];
+            (mine < theirs) as i32 - (theirs < mine) as i32
+        };
+        let area: i32 = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(owner)
+            .sum();
+        let food: i32 = board
+            .food()
+            .iter()
+            .filter(|f| f.kind != FoodKind::Poison)
+            .map(|f| owner(f.pos))
+            .sum();
+        let len =
+            |player: Option<PlayerId>| player.map_or(0, |p| board.snake(p).body().len() as i32);
+
+        area + 2 * food + 4 * (len(Some(self.player)) - len(self.opponent))
+    }
+}
+
+/// Directions the player's snake can move in, reversing isn't one of them
+fn moves(board: &Board, player: PlayerId) -> Vec<Dir> {
+    let snake = board.snake(player);
+    DIRS.into_iter()
+        .filter(|&dir| dir != snake.dir().opposite() || snake.body().len() == 1)
+        .collect()
+}
+
+fn closest_opponent(board: &Board, player: PlayerId) -> Option<PlayerId> {
+    let head = board.snake(player).head();
+    board
+        .snakes()
+        .iter()
+        .filter(|s| s.is_alive() && s.player() != player)
+        .min_by_key(|s| (s.head().0 - head.0).abs() + (s.head().1 - head.1).abs())
+        .map(|s| s.player())
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::board::{Board, Dir, PlayerId, TURN_QUEUE_LEN};
+
+/// Anything that can steer a snake, the game loop asks every controller for
+/// a direction before each update
+pub trait SnakeController {
+    /// Direction the player's snake should turn to on the next update,
+    /// `None` keeps it going the way it goes
+    fn next_dir(&mut self, board: &Board, player: PlayerId) -> Option<Dir>;
+
+    /// Called with every terminal event, before the next `next_dir`
+    fn on_event(&mut self, _event: &Event) {}
+
+    /// Called when a new round starts on the board
+    fn reset(&mut self) {}
+}
+
+/// Keys pressed between updates are kept and handed out one per update, the
+/// same way a player would press them on a slower snake
+///
+/// Presses that wouldn't change the snake's course are skipped when handed
+/// out, so they don't hold up the turn after them. As many presses are kept
+/// as the board queues turns, see [`TURN_QUEUE_LEN`].
+pub struct KeyboardController {
+    keys: Vec<(KeyCode, Dir)>,
+    pressed: VecDeque<Dir>,
+}
+
+impl KeyboardController {
+    pub fn new(keys: Vec<(KeyCode, Dir)>) -> Self {
+        Self {
+            keys,
+            pressed: VecDeque::new(),
+        }
+    }
+
+    pub fn wasd() -> Self {
+        use KeyCode::Char;
+        Self::new(vec![
+            (Char('w'), Dir::Up),
+            (Char('W'), Dir::Up),
+            (Char('a'), Dir::Left),
+            (Char('A'), Dir::Left),
+            (Char('s'), Dir::Down),
+            (Char('S'), Dir::Down),
+            (Char('d'), Dir::Right),
+            (Char('D'), Dir::Right),
+        ])
+    }
+
+    pub fn arrows() -> Self {
+        Self::new(vec![
+            (KeyCode::Up, Dir::Up),
+            (KeyCode::Left, Dir::Left),
+            (KeyCode::Down, Dir::Down),
+            (KeyCode::Right, Dir::Right),
+        ])
+    }
+
+    pub fn ijkl() -> Self {
+        use KeyCode::Char;
+        Self::new(vec![
+            (Char('i'), Dir::Up),
+            (Char('I'), Dir::Up),
+            (Char('j'), Dir::Left),
+            (Char('J'), Dir::Left),
+            (Char('k'), Dir::Down),
+            (Char('K'), Dir::Down),
+            (Char('l'), Dir::Right),
+            (Char('L'), Dir::Right),
+        ])
+    }
+
+    /// Home, Delete, End and Page Down, laid out like the arrow keys on most
+    /// keyboards
+    pub fn nav_keys() -> Self {
+        Self::new(vec![
+            (KeyCode::Home, Dir::Up),
+            (KeyCode::Delete, Dir::Left),
+            (KeyCode::End, Dir::Down),
+            (KeyCode::PageDown, Dir::Right),
+        ])
+    }
+}
+
+impl SnakeController for KeyboardController {
+    fn next_dir(&mut self, board: &Board, player: PlayerId) -> Option<Dir> {
+        while let Some(dir) = self.pressed.pop_front() {
+            if board.changes_course(player, dir) {
+                return Some(dir);
+            }
+        }
+        None
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) = event
+        else {
+            return;
+        };
+
+        let Some(&(_, dir)) = self.keys.iter().find(|(key, _)| key == code) else {
+            return;
+        };
+        // Holding a key down shouldn't fill the whole buffer
+        if self.pressed.len() < TURN_QUEUE_LEN && self.pressed.back() != Some(&dir) {
+            self.pressed.push_back(dir);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pressed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(controller: &mut KeyboardController, code: KeyCode) {
+        controller.on_event(&Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn presses_that_change_nothing_are_skipped() {
+        let mut board = Board::from_ascii("aa1.\n....\n...*").unwrap();
+        let mut controller = KeyboardController::arrows();
+        press(&mut controller, KeyCode::Right);
+        press(&mut controller, KeyCode::Left);
+        press(&mut controller, KeyCode::Up);
+
+        let dir = controller.next_dir(&board, 0);
+        assert_eq!(dir, Some(Dir::Up));
+        board.update(&[dir]);
+        assert_eq!(board.snake(0).dir(), Dir::Up);
+    }
+}
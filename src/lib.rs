@@ -1,3 +1,10 @@
+pub mod ansi;
 pub mod board;
+pub mod bot;
+pub mod controller;
 pub mod helpers;
+pub mod map;
+pub mod net;
+pub mod replay;
+#[cfg(feature = "render")]
 pub mod ui;
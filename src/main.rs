@@ -1,36 +1,87 @@
 use std::{
+    net::TcpListener,
     thread,
     time::{Duration, Instant},
 };
 
 use crossterm::{
     event::{self, KeyCode, KeyModifiers},
-    style::{ContentStyle, Stylize},
+    style::{Color, ContentStyle, Stylize},
+};
+use double_snake::{
+    ansi,
+    board::{
+        Board, BoardConfig, BoardState, DeathCause, EdgeMode, FoodConfig, GameEvent, PlayerId,
+        Speed,
+    },
+    bot::{external::ExternalBot, greedy::GreedyBot, search::SearchBot, Difficulty},
+    controller::{KeyboardController, SnakeController},
+    map::{generator::GeneratorConfig, Map},
+    net::{
+        lockstep::{Input, Lockstep},
+        spectate::SpectatorHub,
+    },
+    replay::Replay,
+    ui::menu::pick,
 };
-use double_snake::board::{Board, BoardState, Dir};
 use terminal_renderer::{
+    canvas::CanvasLike,
     drawable::{misc::RightAlignedStringExt, Drawable},
     frame::Frame,
     renderer::Renderer,
 };
 
+const HELP: &str = "\
+Snake for 2 to 4 players at one keyboard, against bots or over the network
+
+Usage: double-snake [options] [MAP]
+
+Arguments:
+  MAP              Arena to play in, the menu offers empty and random ones
+                   without it
+
+Options:
+  --bot CMD        Player 2 is a bot running as its own program
+  --host ADDR      Waits for another player to join on this address
+  --join ADDR      Joins a match hosted at this address
+  --spectate ADDR  Lets anyone watch on this TCP address, or on a local
+                   socket with unix:PATH
+  --load FILE      Resumes a match saved with F3";
+
+/// Players at one keyboard, every one of them has their own keys
+const MAX_LOCAL_PLAYERS: usize = 4;
+
+/// How far back the instant replay at the end of a round goes
+const INSTANT_REPLAY: Duration = Duration::from_secs(3);
+/// How many times slower than the round itself the instant replay plays
+const INSTANT_REPLAY_SLOWDOWN: u32 = 3;
+
+#[derive(Clone, Copy)]
+enum Opponent {
+    Human,
+    Greedy(Difficulty),
+    Search,
+}
+
 #[derive(Clone, Copy, Default)]
 struct PlayerButtons(bool, bool);
 
 struct GameInput {
-    snake1_dir: Option<Dir>,
-    snake2_dir: Option<Dir>,
     p1: PlayerButtons,
     p2: PlayerButtons,
+    save_replay: bool,
+    save_match: bool,
+    pause: bool,
 }
 
 impl GameInput {
     fn new() -> Self {
         Self {
-            snake1_dir: None,
-            snake2_dir: None,
             p1: PlayerButtons(false, false),
             p2: PlayerButtons(false, false),
+            save_replay: false,
+            save_match: false,
+            pause: false,
         }
     }
 
@@ -55,18 +106,13 @@ impl GameInput {
                 }) => {
                     use KeyCode::*;
                     match code {
-                        Char('w' | 'W') => self.snake1_dir = Some(Dir::Up),
-                        Char('a' | 'A') => self.snake1_dir = Some(Dir::Left),
-                        Char('s' | 'S') => self.snake1_dir = Some(Dir::Down),
-                        Char('d' | 'D') => self.snake1_dir = Some(Dir::Right),
-                        Up => self.snake2_dir = Some(Dir::Up),
-                        Left => self.snake2_dir = Some(Dir::Left),
-                        Down => self.snake2_dir = Some(Dir::Down),
-                        Right => self.snake2_dir = Some(Dir::Right),
                         Char('r' | 'R') => self.p1.0 = true,
                         Char('f' | 'F') => self.p1.1 = true,
                         Char('5') => self.p2.0 = true,
                         Char('2') => self.p2.1 = true,
+                        F(2) => self.save_replay = true,
+                        F(3) => self.save_match = true,
+                        Char(' ') => self.pause = true,
                         _ => {}
                     }
                 }
@@ -77,54 +123,530 @@ impl GameInput {
     }
 }
 
+fn death_message(player: PlayerId, cause: DeathCause) -> String {
+    match cause {
+        DeathCause::Wall => format!("P{} hit the wall", player + 1),
+        DeathCause::OwnBody => format!("P{} bit their own tail", player + 1),
+        DeathCause::Body { owner } => format!("P{} ran into P{}", player + 1, owner + 1),
+        DeathCause::HeadOn { other } => {
+            format!("P{} crashed head-on with P{}", player + 1, other + 1)
+        }
+    }
+}
+
+/// Keys of a human player in the seat and how the menu names them
+fn seat_keys(player: PlayerId) -> (fn() -> KeyboardController, &'static str) {
+    match player {
+        0 => (KeyboardController::wasd, "WASD"),
+        1 => (KeyboardController::arrows, "Arrows"),
+        2 => (KeyboardController::ijkl, "IJKL"),
+        _ => (KeyboardController::nav_keys, "Home Del End PgDn"),
+    }
+}
+
+/// Lets the players pick who steers the snake in the seat, `None` means they
+/// backed out
+fn pick_controller(
+    renderer: &mut Renderer,
+    frame: &mut impl CanvasLike,
+    player: PlayerId,
+) -> Result<Option<Box<dyn SnakeController>>, Box<dyn std::error::Error>> {
+    let (keys, key_names) = seat_keys(player);
+    let human = format!("Human, {}", key_names);
+    let Some(opponent) = pick(
+        renderer,
+        frame,
+        Color::Green,
+        &format!("Player {}", player + 1),
+        &[
+            (Opponent::Human, human.as_str()),
+            (Opponent::Greedy(Difficulty::Easy), "Bot, easy"),
+            (Opponent::Greedy(Difficulty::Normal), "Bot, normal"),
+            (Opponent::Greedy(Difficulty::Hard), "Bot, hard"),
+            (Opponent::Search, "Bot, expert"),
+        ],
+    )?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(match opponent {
+        Opponent::Human => Box::new(keys()),
+        Opponent::Greedy(difficulty) => Box::new(GreedyBot::new(difficulty, rand::random())),
+        Opponent::Search => Box::new(SearchBot::new(Duration::from_millis(60))),
+    }))
+}
+
+/// Lets the player pick the arena and rules of the match for at most
+/// `max_players`, `None` means they backed out
+fn pick_config(
+    renderer: &mut Renderer,
+    frame: &mut impl CanvasLike,
+    map_file: Option<Map>,
+    max_players: usize,
+) -> Result<Option<BoardConfig>, Box<dyn std::error::Error>> {
+    let max_players = map_file
+        .as_ref()
+        .map_or(max_players, |map| max_players.min(map.spawns.len()));
+    let players = if max_players > 2 {
+        let labels: Vec<_> = (2..=max_players)
+            .map(|players| format!("{} players", players))
+            .collect();
+        let options: Vec<_> = (2..=max_players)
+            .zip(labels.iter())
+            .map(|(players, label)| (players, label.as_str()))
+            .collect();
+        let Some(players) = pick(renderer, frame, Color::Green, "Players", &options)? else {
+            return Ok(None);
+        };
+        players
+    } else {
+        2
+    };
+
+    let map = match map_file {
+        Some(map) => map,
+        None => {
+            let Some(density) = pick(
+                renderer,
+                frame,
+                Color::Green,
+                "Arena",
+                &[
+                    (None, "Empty"),
+                    (Some(0.15), "Random, a few walls"),
+                    (Some(0.3), "Random, lots of walls"),
+                ],
+            )?
+            else {
+                return Ok(None);
+            };
+
+            match density {
+                Some(density) => Map::generate(&GeneratorConfig {
+                    players,
+                    density,
+                    seed: rand::random(),
+                    ..Default::default()
+                }),
+                None => Map::default(),
+            }
+        }
+    };
+
+    let Some(edge_mode) = pick(
+        renderer,
+        frame,
+        Color::Green,
+        "Edges",
+        &[
+            (EdgeMode::Wrap, "Wrap around"),
+            (EdgeMode::Wall, "Solid walls"),
+        ],
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let Some(speed) = pick(
+        renderer,
+        frame,
+        Color::Green,
+        "Speed",
+        &[
+            (Speed::default(), "Normal"),
+            (Speed::starting_at(Duration::from_millis(450)), "Relaxed"),
+            (Speed::starting_at(Duration::from_millis(200)), "Fast"),
+            (
+                Speed::constant(Duration::from_millis(300)),
+                "Classic, never speeds up",
+            ),
+        ],
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let Some(food) = pick(
+        renderer,
+        frame,
+        Color::Green,
+        "Food",
+        &[
+            (
+                FoodConfig::classic as fn() -> FoodConfig,
+                "Classic, one at a time",
+            ),
+            (FoodConfig::mixed, "Mixed, a few special ones"),
+            (FoodConfig::chaos, "Chaos"),
+        ],
+    )?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(BoardConfig {
+        map,
+        players,
+        edge_mode,
+        speed,
+        food: food(),
+        ..Default::default()
+    }))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The map is loaded before the terminal is taken over so errors stay readable
+    let mut map_path = None;
+    let mut bot_command = None;
+    let mut host_addr = None;
+    let mut join_addr = None;
+    let mut spectate_addr = None;
+    let mut load_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bot" => bot_command = Some(args.next().ok_or("Missing command after --bot")?),
+            "--host" => host_addr = Some(args.next().ok_or("Missing address after --host")?),
+            "--join" => join_addr = Some(args.next().ok_or("Missing address after --join")?),
+            "--spectate" => {
+                spectate_addr = Some(args.next().ok_or("Missing address after --spectate")?)
+            }
+            "--load" => load_path = Some(args.next().ok_or("Missing file after --load")?),
+            "--help" | "-h" => {
+                println!("{}", HELP);
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown option '{}'\n\n{}", arg, HELP).into())
+            }
+            _ if map_path.is_some() => return Err("Only one map can be played at once".into()),
+            _ => map_path = Some(arg),
+        }
+    }
+    if host_addr.is_some() && join_addr.is_some() {
+        return Err("Cannot host and join at once".into());
+    }
+    if bot_command.is_some() && (host_addr.is_some() || join_addr.is_some()) {
+        return Err("Player 2 is the other side in a network match, not a bot".into());
+    }
+    if join_addr.is_some() && map_path.is_some() {
+        return Err("The host picks the map".into());
+    }
+    if load_path.is_some() && (host_addr.is_some() || join_addr.is_some()) {
+        return Err("Saved matches can only be resumed locally".into());
+    }
+    if load_path.is_some() && map_path.is_some() {
+        return Err("A saved match has its own map".into());
+    }
+
+    let map_file = match map_path {
+        Some(path) => Some(Map::load(&path).map_err(|err| format!("{}: {}", path, err))?),
+        None => None,
+    };
+    // Started early too, a wrong command should fail before any menu shows
+    let mut external_bot = match bot_command {
+        Some(command) => Some(
+            ExternalBot::spawn(&command, Duration::from_millis(100))
+                .map_err(|err| format!("{}: {}", command, err))?,
+        ),
+        None => None,
+    };
+    if map_file.as_ref().is_some_and(|map| map.spawns.len() < 2) {
+        return Err("Map needs spawns for at least 2 players".into());
+    }
+    let saved = match load_path {
+        Some(path) => Some(Board::load(&path).map_err(|err| format!("{}: {}", path, err))?),
+        None => None,
+    };
+    if saved
+        .as_ref()
+        .is_some_and(|board| board.players() < 2 || board.players() > MAX_LOCAL_PLAYERS)
+    {
+        return Err(format!(
+            "Only matches of 2 to {} players can be resumed",
+            MAX_LOCAL_PLAYERS
+        )
+        .into());
+    }
+    let spectators = match spectate_addr {
+        Some(addr) => {
+            Some(SpectatorHub::listen(&addr).map_err(|err| format!("{}: {}", addr, err))?)
+        }
+        None => None,
+    };
+    let listener = match host_addr {
+        Some(addr) => Some(TcpListener::bind(&addr).map_err(|err| format!("{}: {}", addr, err))?),
+        None => None,
+    };
+    // The host picks everything, so joining waits for it before any drawing
+    let joined = match join_addr {
+        Some(addr) => {
+            println!("Waiting for the host at {} to start the match", addr);
+            Some(Lockstep::join(&addr).map_err(|err| format!("{}: {}", addr, err))?)
+        }
+        None => None,
+    };
+
     let mut renderer = Renderer::new()?;
     let mut render_space = renderer.get_render_space();
 
-    let mut board = Board::new();
+    let resumed = saved.is_some();
+    let (mut board, mut lockstep) = match (joined, saved) {
+        (Some((lockstep, config)), _) => (Board::with_config(config), Some(lockstep)),
+        (None, Some(board)) => (board, None),
+        (None, None) => {
+            // Network matches are always between two
+            let max_players = match listener {
+                Some(_) => 2,
+                None => MAX_LOCAL_PLAYERS,
+            };
+            let Some(config) =
+                pick_config(&mut renderer, &mut render_space, map_file, max_players)?
+            else {
+                return Ok(());
+            };
+            match &listener {
+                Some(listener) => {
+                    Frame::new(&mut render_space).clear();
+                    format!("Waiting for a player to join on {}", listener.local_addr()?)
+                        .draw((1, 0), &mut render_space);
+                    renderer.render()?;
+
+                    let config = BoardConfig {
+                        seed: Some(rand::random()),
+                        ..config
+                    };
+                    let lockstep = Lockstep::host(listener, &config)?;
+                    (Board::with_config(config), Some(lockstep))
+                }
+                None => (Board::with_config(config), None),
+            }
+        }
+    };
+
+    board.keep_history(INSTANT_REPLAY);
+
+    // `None` for the snake steered from the other side, its direction comes
+    // from `Lockstep::exchange` below
+    let mut controllers: Vec<Option<Box<dyn SnakeController>>> =
+        vec![Some(Box::new(KeyboardController::wasd()))];
+    for player in 1..board.players() {
+        let controller: Option<Box<dyn SnakeController>> = match (player, &lockstep) {
+            (1, Some(_)) => None,
+            (1, None) if external_bot.is_some() => {
+                external_bot.take().map(|bot| Box::new(bot) as _)
+            }
+            _ => {
+                let Some(controller) = pick_controller(&mut renderer, &mut render_space, player)?
+                else {
+                    return Ok(());
+                };
+                Some(controller)
+            }
+        };
+        controllers.push(controller);
+    }
+    if lockstep.as_ref().is_some_and(|l| l.player() != 0) {
+        // Whoever sits at this keyboard plays with WASD
+        controllers.swap(0, 1);
+    }
+
+    let help = match lockstep {
+        Some(_) => "WASD, confirm: R, back: F; F2: save replay".to_string(),
+        None => {
+            let mut help =
+                "P1: WASD, confirm: R, back: F; P2: Arrows, confirm: 5, back: 2; ".to_string();
+            for player in 2..board.players() {
+                help += &format!("P{}: {}; ", player + 1, seat_keys(player).1);
+            }
+            help + "Space: pause; F2: save replay; F3: save match"
+        }
+    };
+
+    // A resumed round has no replay, it would have to start at the beginning
+    let mut replay = (!resumed).then(|| Replay::new(&board));
 
     let mut last_frame_start = Instant::now();
+    let mut frame_interval = board.update_interval();
     let mut input = GameInput::new();
+    let mut status = String::new();
+    // Resumed matches wait until the players are ready
+    let mut paused = resumed;
+    if paused {
+        status = "Resumed, Space to go on".into();
+    }
+    // Next frame of the instant replay while it's playing
+    let mut instant_replay = None;
 
     loop {
         // Sleep until the next frame should start
         let now = Instant::now();
         let delta = now - last_frame_start;
-        thread::sleep(
-            Board::get_update_intervar()
-                .checked_sub(delta)
-                .unwrap_or_default(),
-        );
+        thread::sleep(frame_interval.checked_sub(delta).unwrap_or_default());
         last_frame_start = Instant::now();
 
-        input.process_available_events(|e| renderer.on_event(&e).unwrap());
+        input.process_available_events(|e| {
+            renderer.on_event(&e).unwrap();
+            for controller in controllers.iter_mut().flatten() {
+                controller.on_event(&e);
+            }
+        });
+
+        if input.pause && board.get_state() == BoardState::Running {
+            if lockstep.is_some() {
+                status = "Network matches can't be paused".into();
+            } else {
+                paused = !paused;
+                status = match paused {
+                    true => "Paused, Space to go on".into(),
+                    false => String::new(),
+                };
+            }
+        }
+
+        // Controllers aren't asked while paused, so no key press gets lost
+        let mut dirs: Vec<_> = match paused {
+            true => vec![None; controllers.len()],
+            false => controllers
+                .iter_mut()
+                .enumerate()
+                .map(|(player, controller)| {
+                    controller
+                        .as_mut()
+                        .and_then(|controller| controller.next_dir(&board, player))
+                })
+                .collect(),
+        };
+        if let Some(lockstep) = &mut lockstep {
+            let theirs = lockstep.exchange(
+                &board,
+                Input {
+                    dir: dirs[lockstep.player()],
+                    confirm: input.p1.0 || input.p2.0,
+                    quit: input.p1.1 || input.p2.1,
+                },
+            )?;
+            dirs[lockstep.opponent()] = theirs.dir;
+            // Buttons pressed on either side count on both to keep them in step
+            input.p1.0 |= theirs.confirm;
+            input.p1.1 |= theirs.quit;
+        }
+
         if input.p1.1 || input.p2.1 {
             break;
         }
 
-        if board.get_state() != BoardState::Running && (input.p1.0 || input.p2.0) {
-            board.reset();
+        // The first confirm only skips the rest of the instant replay
+        if board.get_state() != BoardState::Running
+            && (input.p1.0 || input.p2.0)
+            && instant_replay.take().is_none()
+        {
+            match lockstep {
+                // Both sides need the same next round, so the seed counts up
+                Some(_) => {
+                    board = Board::with_config(BoardConfig {
+                        seed: Some(board.seed().wrapping_add(1)),
+                        ..board.config().clone()
+                    });
+                    board.keep_history(INSTANT_REPLAY);
+                }
+                None => board.reset(),
+            }
+            replay = Some(Replay::new(&board));
+            for controller in controllers.iter_mut().flatten() {
+                controller.reset();
+            }
+            frame_interval = board.update_interval();
+            status.clear();
             continue;
         }
 
-        board.update(input.snake1_dir, input.snake2_dir);
+        if input.save_replay {
+            let path = format!("replay-{}.json", board.seed());
+            status = match &replay {
+                Some(replay) => match replay.save(&path) {
+                    Ok(()) => format!("Replay saved to {}", path),
+                    Err(err) => err.to_string(),
+                },
+                None => "Resumed rounds have no replay".into(),
+            };
+        }
+        if input.save_match {
+            let path = format!("match-{}.json", board.seed());
+            status = match board.save(&path) {
+                Ok(()) => format!("Match saved to {}", path),
+                Err(err) => err.to_string(),
+            };
+        }
 
-        "P1: WASD, confirm: R, back: F; P2: Arrows, confirm: 5, back: 2"
-            .draw((1, 0), &mut render_space);
-        (
-            format!("P1 Score: {}", board.get_scores().0),
-            ContentStyle::new().green(),
-        )
-            .draw((1, 1), &mut render_space);
+        let was_running = board.get_state() == BoardState::Running;
+        if was_running && !paused {
+            if let Some(replay) = &mut replay {
+                replay.record(&dirs);
+            }
+        }
+        let events = match paused {
+            true => Vec::new(),
+            false => board.update(&dirs),
+        };
+        let deaths: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                GameEvent::Died { player, cause } => Some(death_message(player, cause)),
+                _ => None,
+            })
+            .collect();
+        if !deaths.is_empty() {
+            status = deaths.join(", ");
+        }
+        if was_running && board.get_state() != BoardState::Running {
+            instant_replay = Some(0);
+        }
+
+        help.as_str().draw((1, 0), &mut render_space);
+        let mut score_x = 1;
+        for (player, score) in board.get_scores().into_iter().enumerate() {
+            let score = format!("P{} Score: {}", player + 1, score);
+            let width = score.chars().count() as i32 + 3;
+            (score, ContentStyle::new().with(board.player_color(player)))
+                .draw((score_x, 1), &mut render_space);
+            score_x += width;
+        }
+        let replay_frames = board.instant_replay();
+        let looks = match instant_replay.and_then(|frame| replay_frames.get(frame)) {
+            Some(frame) => {
+                frame.draw((0, 2), &mut render_space);
+                frame_interval = frame.interval() * INSTANT_REPLAY_SLOWDOWN;
+                instant_replay = instant_replay
+                    .map(|frame| frame + 1)
+                    .filter(|&frame| frame < replay_frames.len());
+                frame.cell_looks()
+            }
+            None => {
+                instant_replay = None;
+                board.draw((0, 2).into(), &mut render_space);
+                frame_interval = board.update_interval();
+                board.cell_looks()
+            }
+        };
         (
-            format!("P2 Score: {}", board.get_scores().1).right(),
-            ContentStyle::new().blue(),
+            format!("Seed: {}", board.seed()),
+            ContentStyle::new().dark_grey(),
         )
-            .draw(
-                (1, 1),
-                &mut Frame::new(&mut render_space).l(board.render_size().0).mx(1),
-            );
-        board.draw((0, 2).into(), &mut render_space);
+            .draw((1, 2 + board.render_size().1), &mut render_space);
+        (status.clone().right(), ContentStyle::new().red()).draw(
+            (1, 2 + board.render_size().1),
+            &mut Frame::new(&mut render_space).l(board.render_size().0).mx(1),
+        );
+        if let Some(spectators) = &spectators {
+            let status = match ansi::round_result(board.get_state()) {
+                Some(result) if !status.is_empty() => format!("{}, {}", status, result),
+                Some(result) => result,
+                None => status.clone(),
+            };
+            spectators.broadcast(ansi::spectator_frame(&board, looks, &status));
+        }
 
         renderer.render()?;
     }
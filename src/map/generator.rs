@@ -0,0 +1,172 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::Map;
+use crate::board::{Dims, Dir};
+
+/// Walls are never placed this close to a spawn, so nobody starts boxed in
+const SPAWN_CLEARANCE: i32 = 2;
+
+/// Most players [`Map::generate`] takes, only the corners are spawns that
+/// mirroring turns into each other
+pub const MAX_GENERATED_PLAYERS: usize = 4;
+
+/// Settings for [`Map::generate`]
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    pub size: Dims,
+    /// From 1 to [`MAX_GENERATED_PLAYERS`]
+    pub players: usize,
+    /// Share of the cells that should become walls, fewer are placed when
+    /// more would cut the arena apart
+    pub density: f64,
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            size: (8, 8),
+            players: 2,
+            density: 0.15,
+            seed: 0,
+        }
+    }
+}
+
+impl Map {
+    /// Random arena in which every free cell can be reached from every other
+    ///
+    /// Players spawn in the corners and walls are symmetric so no spawn is
+    /// better than the others, turned by half a turn around the center for
+    /// two players and mirrored on both axes for three or four. The same
+    /// config always gives the same map.
+    ///
+    /// Panics for more than [`MAX_GENERATED_PLAYERS`] players, there are no
+    /// more spawns that would be as good as the corners.
+    pub fn generate(config: &GeneratorConfig) -> Self {
+        if config.players == 0 || config.players > MAX_GENERATED_PLAYERS {
+            panic!("Map must have 1 to {} players", MAX_GENERATED_PLAYERS);
+        }
+
+        let (width, height) = config.size;
+        let mut map = Map::empty(width, height);
+        map.spawns.truncate(config.players);
+
+        let near_spawn = |pos: Dims| {
+            map.spawns
+                .iter()
+                .any(|s| (s.0 - pos.0).abs() + (s.1 - pos.1).abs() <= SPAWN_CLEARANCE)
+        };
+        let mut orbits: Vec<_> = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(|pos| (pos, orbit(config.size, config.players, pos)))
+            .filter(|(pos, orbit)| orbit[0] == *pos) // Only the first cell of each
+            .map(|(_, orbit)| orbit)
+            .filter(|orbit| !orbit.iter().any(|&pos| near_spawn(pos)))
+            .collect();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        orbits.shuffle(&mut rng);
+
+        let target = (config.density.clamp(0., 1.) * (width * height) as f64) as usize;
+        for orbit in orbits {
+            if map.walls.len() + orbit.len() > target {
+                continue;
+            }
+
+            map.walls.extend(orbit.iter().copied());
+            if !is_connected(&map) {
+                for pos in orbit.iter() {
+                    map.walls.remove(pos);
+                }
+            }
+        }
+
+        map
+    }
+}
+
+/// Cells that `pos` is mapped to by the symmetry used for that many players,
+/// sorted and including `pos` itself
+fn orbit(size: Dims, players: usize, pos: Dims) -> Vec<Dims> {
+    let (width, height) = size;
+    let (x, y) = pos;
+    let mut cells = if players <= 2 {
+        vec![pos, (width - 1 - x, height - 1 - y)]
+    } else {
+        vec![
+            pos,
+            (width - 1 - x, y),
+            (x, height - 1 - y),
+            (width - 1 - x, height - 1 - y),
+        ]
+    };
+    cells.sort();
+    cells.dedup();
+    cells
+}
+
+/// Whether all free cells form a single area, edges are taken as solid so
+/// it also holds when they wrap around
+fn is_connected(map: &Map) -> bool {
+    let free = map.size.0 * map.size.1 - map.walls.len() as i32;
+    let Some(start) = (0..map.size.0)
+        .flat_map(|x| (0..map.size.1).map(move |y| (x, y)))
+        .find(|&pos| !map.is_wall(pos))
+    else {
+        return true;
+    };
+
+    let mut seen = BTreeSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(pos) = queue.pop_front() {
+        for dir in [Dir::Up, Dir::Down, Dir::Left, Dir::Right] {
+            let next = dir.step(pos);
+            if map.contains(next) && !map.is_wall(next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    seen.len() as i32 == free
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_maps_are_connected_and_symmetric() {
+        for players in 1..=MAX_GENERATED_PLAYERS {
+            for (size, seed) in [((8, 8), 1), ((12, 8), 2), ((9, 7), 3), ((16, 11), 4)] {
+                let config = GeneratorConfig {
+                    size,
+                    players,
+                    density: 0.3,
+                    seed,
+                };
+                let map = Map::generate(&config);
+
+                assert!(!map.walls.is_empty(), "{:?}", config);
+                assert!(is_connected(&map), "{:?}", config);
+                for &wall in map.walls.iter() {
+                    assert!(
+                        orbit(size, players, wall)
+                            .iter()
+                            .all(|&pos| map.is_wall(pos)),
+                        "{:?}",
+                        config
+                    );
+                }
+                for &spawn in map.spawns.iter() {
+                    assert!(!map.is_wall(spawn));
+                    // Every spawn is where the symmetry takes the first one
+                    assert!(orbit(size, players, map.spawns[0]).contains(&spawn));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::BTreeSet, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Dims, MAX_PLAYERS};
+
+pub mod generator;
+
+/// Layout of an arena, loaded from a plain text file
+///
+/// Every line of the file is one row of the grid, all rows have the same
+/// length. Lines starting with `;` are comments.
+///
+/// - `.` empty cell
+/// - `#` wall, kills a snake on contact
+/// - `1` to `8` spawn point of the player
+/// - `*` food at the start of the round
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Map {
+    pub size: Dims,
+    pub walls: BTreeSet<Dims>,
+    /// Spawn point of each player, indexed by player
+    pub spawns: Vec<Dims>,
+    pub food: Vec<Dims>,
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+    Empty,
+    /// Boards need at least 2 rows and 2 columns
+    TooSmall,
+    /// Row has a different length than the first one
+    Ragged {
+        line: usize,
+    },
+    UnknownChar {
+        ch: char,
+        line: usize,
+        column: usize,
+    },
+    DuplicateSpawn {
+        player: usize,
+    },
+    /// Spawn of this player is missing while a later one is there
+    MissingSpawn {
+        player: usize,
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "Cannot read map: {}", err),
+            MapError::Empty => write!(f, "Map has no rows"),
+            MapError::TooSmall => write!(f, "Map must be at least 2x2"),
+            MapError::Ragged { line } => write!(f, "Row on line {} has a different length", line),
+            MapError::UnknownChar { ch, line, column } => {
+                write!(f, "Unknown cell '{}' on line {}:{}", ch, line, column)
+            }
+            MapError::DuplicateSpawn { player } => {
+                write!(f, "Player {} has more than one spawn", player + 1)
+            }
+            MapError::MissingSpawn { player } => write!(f, "Player {} has no spawn", player + 1),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<io::Error> for MapError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Map {
+    /// Map without walls, players spawn in the corners first and then in the
+    /// middles of the edges
+    pub fn empty(width: i32, height: i32) -> Self {
+        Self {
+            size: (width, height),
+            walls: BTreeSet::new(),
+            spawns: vec![
+                (0, 0),
+                (width - 1, height - 1),
+                (width - 1, 0),
+                (0, height - 1),
+                (width / 2, 0),
+                (width - 1 - width / 2, height - 1),
+                (0, height / 2),
+                (width - 1, height - 1 - height / 2),
+            ],
+            food: Vec::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MapError> {
+        let rows: Vec<_> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with(';'))
+            .collect();
+        let width = rows.first().ok_or(MapError::Empty)?.1.chars().count();
+        if width < 2 || rows.len() < 2 {
+            return Err(MapError::TooSmall);
+        }
+
+        let mut walls = BTreeSet::new();
+        let mut spawns = [None; MAX_PLAYERS];
+        let mut food = Vec::new();
+        for (y, &(line, row)) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(MapError::Ragged { line });
+            }
+
+            for (x, ch) in row.chars().enumerate() {
+                let pos = (x as i32, y as i32);
+                match ch {
+                    '.' => {}
+                    '#' => {
+                        walls.insert(pos);
+                    }
+                    '*' => food.push(pos),
+                    '1'..='8' => {
+                        let player = ch as usize - '1' as usize;
+                        if spawns[player].replace(pos).is_some() {
+                            return Err(MapError::DuplicateSpawn { player });
+                        }
+                    }
+                    _ => {
+                        return Err(MapError::UnknownChar {
+                            ch,
+                            line,
+                            column: x + 1,
+                        })
+                    }
+                }
+            }
+        }
+
+        let count = spawns
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1);
+        let spawns = spawns[..count]
+            .iter()
+            .enumerate()
+            .map(|(player, spawn)| spawn.ok_or(MapError::MissingSpawn { player }))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            size: (width as i32, rows.len() as i32),
+            walls,
+            spawns,
+            food,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn is_wall(&self, pos: Dims) -> bool {
+        self.walls.contains(&pos)
+    }
+
+    pub fn contains(&self, pos: Dims) -> bool {
+        pos.0 >= 0 && pos.1 >= 0 && pos.0 < self.size.0 && pos.1 < self.size.1
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::empty(8, 8)
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.size.1 {
+            for x in 0..self.size.0 {
+                let pos = (x, y);
+                let ch = if let Some(player) = self.spawns.iter().position(|&s| s == pos) {
+                    (b'1' + player as u8) as char
+                } else if self.is_wall(pos) {
+                    '#'
+                } else if self.food.contains(&pos) {
+                    '*'
+                } else {
+                    '.'
+                };
+                write!(f, "{}", ch)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::NetError;
+use crate::board::{Board, BoardConfig, Dir, PlayerId};
+
+/// Player steered by the hosting side
+pub const HOST_PLAYER: PlayerId = 0;
+/// Player steered by the joining side
+pub const GUEST_PLAYER: PlayerId = 1;
+
+/// What one side did before an update
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    pub dir: Option<Dir>,
+    /// Starts the next round once the current one is over
+    pub confirm: bool,
+    pub quit: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// First message of the host, both sides play with this config
+    Start { config: Box<BoardConfig> },
+    /// Input for the update after `tick`, with the checksum of the sender's
+    /// board at that point
+    Input {
+        tick: u32,
+        checksum: u64,
+        input: Input,
+    },
+}
+
+/// Connection between two instances of the game playing one match
+///
+/// Only inputs go over the wire, one JSON message per line. Before every
+/// update each side sends its own input and waits for the other's, then
+/// both run the same update on their own board. Every message carries the
+/// checksum of the sender's board, so boards that got out of sync are
+/// noticed on the next update instead of quietly playing different rounds.
+pub struct Lockstep {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    player: PlayerId,
+}
+
+impl Lockstep {
+    /// Waits for the other side to connect and sends it the config, which
+    /// must have the seed set
+    pub fn host(listener: &TcpListener, config: &BoardConfig) -> Result<Self, NetError> {
+        if config.seed.is_none() {
+            panic!("Both sides need the same seed");
+        }
+
+        let (stream, _) = listener.accept()?;
+        let mut lockstep = Self::new(stream, HOST_PLAYER)?;
+        lockstep.send(&Message::Start {
+            config: Box::new(config.clone()),
+        })?;
+        Ok(lockstep)
+    }
+
+    /// Connects to a host and waits for the config of the match, which is
+    /// checked to make a board for two players
+    pub fn join(addr: impl ToSocketAddrs) -> Result<(Self, BoardConfig), NetError> {
+        let mut lockstep = Self::new(TcpStream::connect(addr)?, GUEST_PLAYER)?;
+        match lockstep.receive()? {
+            Message::Start { config } => {
+                if let Err(err) = config.check() {
+                    return Err(NetError::Protocol(format!("unplayable match, {}", err)));
+                }
+                if config.players != 2 {
+                    return Err(NetError::Protocol("match isn't for 2 players".into()));
+                }
+                if config.seed.is_none() {
+                    return Err(NetError::Protocol("match has no seed".into()));
+                }
+                Ok((lockstep, *config))
+            }
+            Message::Input { .. } => Err(NetError::Protocol("input before the start".into())),
+        }
+    }
+
+    fn new(stream: TcpStream, player: PlayerId) -> Result<Self, NetError> {
+        // Messages are tiny and every one of them is waited for
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            player,
+        })
+    }
+
+    /// Player steered from this side
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Player steered from the other side
+    pub fn opponent(&self) -> PlayerId {
+        match self.player {
+            HOST_PLAYER => GUEST_PLAYER,
+            _ => HOST_PLAYER,
+        }
+    }
+
+    /// Sends this side's input for the next update of `board` and waits for
+    /// the other side's
+    pub fn exchange(&mut self, board: &Board, input: Input) -> Result<Input, NetError> {
+        let tick = board.ticks();
+        let checksum = board.checksum();
+        self.send(&Message::Input {
+            tick,
+            checksum,
+            input,
+        })?;
+
+        match self.receive()? {
+            Message::Input {
+                tick: their_tick,
+                checksum: their_checksum,
+                input,
+            } => {
+                if their_tick != tick || their_checksum != checksum {
+                    return Err(NetError::Desync { tick });
+                }
+                Ok(input)
+            }
+            Message::Start { .. } => Err(NetError::Protocol("match started twice".into())),
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        // One write per message, small writes would each be a packet
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Message, NetError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(NetError::Disconnected);
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        board::{BoardState, EdgeMode},
+        map::Map,
+    };
+
+    fn config() -> BoardConfig {
+        BoardConfig {
+            edge_mode: EdgeMode::Wall,
+            seed: Some(7),
+            ..Default::default()
+        }
+    }
+
+    /// Plays a round on one side, turning with `turns` on the given ticks
+    fn play(mut lockstep: Lockstep, mut board: Board, turns: &[(u32, Dir)]) -> Board {
+        while board.get_state() == BoardState::Running {
+            let dir = turns
+                .iter()
+                .find(|(tick, _)| *tick == board.ticks())
+                .map(|(_, dir)| *dir);
+            let theirs = lockstep.exchange(
+                &board,
+                Input {
+                    dir,
+                    ..Default::default()
+                },
+            );
+            let mut dirs = vec![None; board.players()];
+            dirs[lockstep.player()] = dir;
+            dirs[lockstep.opponent()] = theirs.unwrap().dir;
+            board.update(&dirs);
+        }
+        board
+    }
+
+    #[test]
+    fn both_sides_play_the_same_round() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let guest = thread::spawn(move || {
+            let (lockstep, config) = Lockstep::join(addr).unwrap();
+            play(
+                lockstep,
+                Board::with_config(config),
+                &[(2, Dir::Up), (4, Dir::Left)],
+            )
+        });
+
+        let lockstep = Lockstep::host(&listener, &config()).unwrap();
+        let host = play(lockstep, Board::with_config(config()), &[(1, Dir::Down)]);
+        let guest = guest.join().unwrap();
+
+        assert_eq!(host.ticks(), guest.ticks());
+        assert_eq!(host.checksum(), guest.checksum());
+        assert_eq!(host.get_state(), guest.get_state());
+    }
+
+    #[test]
+    fn unplayable_configs_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let guest = thread::spawn(move || Lockstep::join(addr).map(|_| ()));
+
+        // More players than the map has spawns would panic in `Board::with_config`
+        let config = BoardConfig {
+            map: Map::empty(2, 2),
+            players: 8,
+            ..config()
+        };
+        Lockstep::host(&listener, &config).unwrap();
+        assert!(matches!(guest.join().unwrap(), Err(NetError::Protocol(_))));
+    }
+
+    #[test]
+    fn different_boards_are_a_desync() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let guest = thread::spawn(move || {
+            let (mut lockstep, config) = Lockstep::join(addr).unwrap();
+            let mut board = Board::with_config(config);
+            board.update(&[None, None]);
+            lockstep.exchange(&board, Input::default())
+        });
+
+        let mut lockstep = Lockstep::host(&listener, &config()).unwrap();
+        let board = Board::with_config(config());
+        assert!(matches!(
+            lockstep.exchange(&board, Input::default()),
+            Err(NetError::Desync { tick: 0 })
+        ));
+        assert!(matches!(
+            guest.join().unwrap(),
+            Err(NetError::Desync { tick: 1 })
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+pub mod lockstep;
+pub mod spectate;
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// The other side sent something that isn't a message
+    Format(serde_json::Error),
+    /// A message came that doesn't fit at this point
+    Protocol(String),
+    Disconnected,
+    /// Both sides ran the same updates and still got different boards
+    Desync {
+        tick: u32,
+    },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(err) => write!(f, "Connection failed: {}", err),
+            NetError::Format(err) => write!(f, "Broken message: {}", err),
+            NetError::Protocol(message) => write!(f, "Unexpected message: {}", message),
+            NetError::Disconnected => write!(f, "The other side disconnected"),
+            NetError::Desync { tick } => write!(f, "Boards got out of sync on tick {}", tick),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for NetError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Format(error)
+    }
+}
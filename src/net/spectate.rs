@@ -0,0 +1,173 @@
+use std::{
+    io::{self, Write},
+    net::TcpListener,
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::Hide,
+    queue,
+    terminal::{Clear, ClearType},
+};
+
+/// Viewers that can't take a frame in this time are dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+/// Frames waiting for a slow viewer, newer ones are skipped while it's full
+const FRAME_QUEUE_LEN: usize = 2;
+
+/// Read-only viewers of a match, connected over TCP or a local socket
+///
+/// The match sends its whole view as one ANSI frame after every update and
+/// the hub passes it on to every viewer. Viewers who connect in the middle
+/// of a match get the latest frame right away, so they see the full board
+/// before the next update.
+///
+/// Every viewer is written to on its own thread, so a slow or stalled viewer
+/// only misses frames and never holds up the match.
+pub struct SpectatorHub {
+    shared: Arc<Mutex<Shared>>,
+    addr: String,
+}
+
+#[derive(Default)]
+struct Shared {
+    viewers: Vec<SyncSender<Arc<Vec<u8>>>>,
+    last_frame: Arc<Vec<u8>>,
+}
+
+impl SpectatorHub {
+    /// Starts taking viewers on `addr`, a TCP address or `unix:PATH` for a
+    /// local socket
+    pub fn listen(addr: &str) -> io::Result<Self> {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let addr = match addr.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => {
+                use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+                // Left behind by an earlier run that didn't get to clean up
+                if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                let listener = UnixListener::bind(path)?;
+                let accepted = shared.clone();
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+                            add_viewer(&accepted, stream);
+                        }
+                    }
+                });
+                format!("unix:{}", path)
+            }
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Local sockets only work on unix",
+                ))
+            }
+            None => {
+                let listener = TcpListener::bind(addr)?;
+                let local_addr = listener.local_addr()?.to_string();
+                let accepted = shared.clone();
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        if stream.set_nodelay(true).is_ok()
+                            && stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok()
+                        {
+                            add_viewer(&accepted, stream);
+                        }
+                    }
+                });
+                local_addr
+            }
+        };
+
+        Ok(Self { shared, addr })
+    }
+
+    /// Where viewers connect to
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Number of viewers connected right now
+    pub fn viewers(&self) -> usize {
+        self.shared.lock().unwrap().viewers.len()
+    }
+
+    /// Hands a frame to every viewer without waiting for them, viewers still
+    /// busy with earlier frames skip it
+    ///
+    /// Late joiners and viewers who skipped frames only get the later ones,
+    /// so every frame has to draw the whole view.
+    pub fn broadcast(&self, frame: Vec<u8>) {
+        let frame = Arc::new(frame);
+        let mut shared = self.shared.lock().unwrap();
+        shared
+            .viewers
+            .retain(|viewer| match viewer.try_send(frame.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                // Its thread gave up after a failed write
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        shared.last_frame = frame;
+    }
+}
+
+/// Starts the thread writing frames to the viewer, beginning with the last
+/// one
+fn add_viewer(shared: &Mutex<Shared>, mut viewer: impl Write + Send + 'static) {
+    let (sender, frames) = mpsc::sync_channel::<Arc<Vec<u8>>>(FRAME_QUEUE_LEN);
+    let last_frame = {
+        let mut shared = shared.lock().unwrap();
+        shared.viewers.push(sender);
+        shared.last_frame.clone()
+    };
+
+    thread::spawn(move || {
+        let mut welcome = Vec::new();
+        if queue!(welcome, Hide, Clear(ClearType::All)).is_err() {
+            return;
+        }
+        welcome.extend_from_slice(&last_frame);
+        if viewer.write_all(&welcome).is_err() {
+            return;
+        }
+        for frame in frames {
+            if viewer.write_all(&frame).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpStream, time::Instant};
+
+    use super::*;
+
+    #[test]
+    fn stalled_viewer_doesnt_hold_up_the_match() {
+        let hub = SpectatorHub::listen("127.0.0.1:0").unwrap();
+        // Never reads, so its socket buffer fills up after the first frame
+        let _stalled = TcpStream::connect(hub.addr()).unwrap();
+        while hub.viewers() == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let start = Instant::now();
+        for _ in 0..20 {
+            hub.broadcast(vec![b'x'; 1 << 20]);
+        }
+        assert!(start.elapsed() < WRITE_TIMEOUT);
+    }
+}
@@ -0,0 +1,192 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::board::{Board, BoardConfig, Dir};
+
+/// Everything needed to play a round again
+///
+/// The board only depends on its config, the seed and the directions given
+/// to every update, so that's all a replay holds. In the file the
+/// directions of one update are a string with a letter per player, `U`,
+/// `D`, `L`, `R` or `.` for none.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// Config of the round, always with the seed set
+    pub config: BoardConfig,
+    #[serde(serialize_with = "write_inputs", deserialize_with = "read_inputs")]
+    inputs: Vec<Vec<Option<Dir>>>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// File is well formed but can't be played
+    Invalid(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "Cannot access replay: {}", err),
+            ReplayError::Format(err) => write!(f, "Broken replay: {}", err),
+            ReplayError::Invalid(reason) => write!(f, "Broken replay: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Format(error)
+    }
+}
+
+impl Replay {
+    /// Starts recording a round, `board` has to be right at its start
+    pub fn new(board: &Board) -> Self {
+        Self {
+            config: BoardConfig {
+                seed: Some(board.seed()),
+                ..board.config().clone()
+            },
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Records the directions given to one update of a running board
+    pub fn record(&mut self, dirs: &[Option<Dir>]) {
+        self.inputs.push(dirs.to_vec());
+    }
+
+    /// Number of recorded updates
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Directions given to the update that started at `tick`
+    pub fn inputs(&self, tick: usize) -> &[Option<Dir>] {
+        &self.inputs[tick]
+    }
+
+    /// Board as it was after `tick` updates, played again from the start
+    pub fn board_at(&self, tick: usize) -> Board {
+        let mut board = Board::with_config(self.config.clone());
+        for dirs in &self.inputs[..tick.min(self.inputs.len())] {
+            board.update(dirs);
+        }
+        board
+    }
+
+    /// Replay saved with [`Replay::save`], checked to play without trouble
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let replay: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        replay.config.check().map_err(ReplayError::Invalid)?;
+        if replay.config.seed.is_none() {
+            return Err(ReplayError::Invalid("Seed is missing".into()));
+        }
+        let players = replay.config.players;
+        if let Some(tick) = replay.inputs.iter().position(|dirs| dirs.len() != players) {
+            return Err(ReplayError::Invalid(format!(
+                "Update {} doesn't have directions for {} players",
+                tick + 1,
+                players
+            )));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+fn write_inputs<S: Serializer>(inputs: &[Vec<Option<Dir>>], s: S) -> Result<S::Ok, S::Error> {
+    let letter = |dir: &Option<Dir>| match dir {
+        Some(Dir::Up) => 'U',
+        Some(Dir::Down) => 'D',
+        Some(Dir::Left) => 'L',
+        Some(Dir::Right) => 'R',
+        None => '.',
+    };
+    s.collect_seq(
+        inputs
+            .iter()
+            .map(|dirs| dirs.iter().map(letter).collect::<String>()),
+    )
+}
+
+fn read_inputs<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<Option<Dir>>>, D::Error> {
+    let lines = Vec::<String>::deserialize(d)?;
+    lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|ch| match ch {
+                    'U' => Ok(Some(Dir::Up)),
+                    'D' => Ok(Some(Dir::Down)),
+                    'L' => Ok(Some(Dir::Left)),
+                    'R' => Ok(Some(Dir::Right)),
+                    '.' => Ok(None),
+                    _ => Err(serde::de::Error::custom(format!(
+                        "unknown direction '{}'",
+                        ch
+                    ))),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves a short replay, lets `edit` break the file and loads it again
+    fn load_edited(
+        name: &str,
+        edit: impl Fn(&mut serde_json::Value),
+    ) -> Result<Replay, ReplayError> {
+        let mut board = Board::new_with_seed(1);
+        let mut replay = Replay::new(&board);
+        for dirs in [[None, Some(Dir::Up)], [Some(Dir::Right), None]] {
+            replay.record(&dirs);
+            board.update(&dirs);
+        }
+        let mut json = serde_json::to_value(&replay).unwrap();
+        edit(&mut json);
+
+        let path =
+            std::env::temp_dir().join(format!("replay-{}-{}.json", name, std::process::id()));
+        fs::write(&path, json.to_string()).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn replays_that_cant_be_played_are_rejected() {
+        assert!(load_edited("ok", |_| {}).is_ok());
+        assert!(matches!(
+            load_edited("players", |json| json["config"]["players"] = 9.into()),
+            Err(ReplayError::Invalid(_))
+        ));
+        assert!(matches!(
+            load_edited("row", |json| json["inputs"][1] = "U.R".into()),
+            Err(ReplayError::Invalid(_))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use crossterm::style::{Color, ContentStyle};
+use crossterm::style::{Color, ContentStyle, Stylize};
 pub use crossterm::{
     event::{poll, read, Event, KeyCode, KeyEvent},
     terminal::size,
@@ -127,6 +127,32 @@ pub fn choice_menu<'a, T>(
         .0)
 }
 
+/// [`choice_menu`] starting on the first option with the box in `color`,
+/// `None` means the user backed out
+pub fn pick<T: Copy>(
+    renderer: &mut Renderer,
+    frame: &mut impl CanvasLike,
+    color: Color,
+    title: &str,
+    options: &[(T, &str)],
+) -> crossterm::Result<Option<T>> {
+    match choice_menu(
+        renderer,
+        frame,
+        ContentStyle::new().with(color),
+        ContentStyle::new(),
+        title,
+        options,
+        Some(0),
+        true,
+    ) {
+        Ok(choice) => Ok(Some(*choice)),
+        Err(MenuError::Exit | MenuError::FullQuit) => Ok(None),
+        Err(MenuError::CrosstermError(err)) => Err(err.0),
+        Err(MenuError::EmptyMenu) => unreachable!("Menu has no options"),
+    }
+}
+
 pub struct Menu<'a> {
     pub title: &'a str,
     pub options: &'a [&'a str],